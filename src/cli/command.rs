@@ -1,26 +1,380 @@
-use crate::{docker::Container, project::Project};
-use anyhow::Result;
+use super::{
+    complete,
+    prompt::{confirm, select},
+    show::{self, Effective},
+    status::{Format, Status},
+};
+use crate::{
+    config,
+    docker::{Container, Ephemeral, LogOptions, Pruner, Reaper},
+    error::DevspaceError,
+    project::{ambiguous_configs, Project},
+    template::{self, Template},
+};
+use anyhow::{anyhow, Context, Result};
+use clap::Command;
+use clap_complete::Shell;
+use std::{
+    io::{stdin, stdout, IsTerminal},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{info, warn};
 
-pub fn shell(root: &str, stop: &bool) -> Result<()> {
-    let project = Project::try_from(root)?;
-    let container: Container = Container::try_from(&project)?;
+fn load_project(root: &str, config: &Option<String>) -> Result<Project> {
+    let project = Project::load(Path::new(root), config.as_deref(), |configs| {
+        if !stdin().is_terminal() {
+            return Err(ambiguous_configs(configs));
+        }
+        let options: Vec<String> = configs.iter().map(|c| c.to_string()).collect();
+        select("multiple devcontainer configs found", &options)
+    })?;
+    for warning in &project.config.warnings {
+        warn!("{}", warning);
+    }
+    Ok(project)
+}
+
+pub fn init(root: &str, template: &Option<String>, force: &bool) -> Result<()> {
+    let root =
+        std::fs::canonicalize(root).with_context(|| format!("invalid project root {:?}", root))?;
+    let name = match template {
+        Some(name) => name.to_string(),
+        None => template::detect(&root).to_string(),
+    };
+    let template = Template::find(&name, &config::Config::templates_dir()?)?;
+    info!("using template {}", template.name);
+    let project_name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    for path in template.write(&root, &project_name, *force)? {
+        info!("created {}", path.display());
+    }
+    Ok(())
+}
+
+/// `stop` overrides the shutdownAction of the config
+pub fn shell(root: &str, config: &Option<String>, stop: &Option<bool>) -> Result<()> {
+    let project = load_project(root, config)?;
+    let global = config::Config::new()?;
+    let shell = show::shell(&project, &global);
+    let container = Container::try_from(&project)?.share_host(&global);
 
     if !container.existing() {
-        println!("container does not exist, creating...");
+        info!("container does not exist, creating...");
         container.setup()?;
     } else if !container.running() {
-        println!("container is not running, starting...");
+        info!("container is not running, starting...");
         container.start()?;
     }
 
-    container.exec(&["/bin/zsh"])?;
+    container.attach()?;
+    let result = container
+        .exec(&[&shell.value])
+        .map_err(DevspaceError::user_command);
+    let sessions = container.detach()?;
+
+    // stop even when the shell exits with a failure, which is usually just its last command's
+    let action = project.config.shutdown_action.unwrap_or_default();
+    if stop.unwrap_or(action.stops()) {
+        if sessions == 0 {
+            container.stop()?;
+        } else {
+            info!("{} other session(s) attached, not stopping", sessions);
+        }
+    }
+
+    result
+}
+
+pub fn run(root: &str, config: &Option<String>, cmd: &[String]) -> Result<()> {
+    let project = load_project(root, config)?;
+    let cmd: Vec<&str> = cmd.iter().map(|s| s.as_str()).collect();
+    let tty = stdin().is_terminal() && stdout().is_terminal();
+    Ephemeral::new(&project)?.run(&cmd, tty)
+}
+
+pub fn logs(
+    root: &str,
+    config: &Option<String>,
+    options: &LogOptions,
+    lifecycle: &bool,
+) -> Result<()> {
+    let project = load_project(root, config)?;
+    let container: Container = Container::try_from(&project)?;
+    if !*lifecycle {
+        return container.logs(options);
+    }
+
+    let log = container.lifecycle_log()?;
+    if !log.exists() {
+        info!("no lifecycle commands run yet");
+        return Ok(());
+    }
+    let content = std::fs::read_to_string(&log)
+        .with_context(|| format!("failed to read log file {:?}", log))?;
+    let lines: Vec<&str> = content.lines().collect();
+    let start = options.tail.map_or(0, |n| lines.len().saturating_sub(n));
+    for line in &lines[start..] {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+pub fn cp(root: &str, config: &Option<String>, src: &str, dst: &str) -> Result<()> {
+    let project = load_project(root, config)?;
+    let container: Container = Container::try_from(&project)?;
+    if !container.existing() {
+        return Err(anyhow!(
+            "container {} does not exist",
+            project.container_name
+        ));
+    }
+
+    match (src.strip_prefix(':'), dst.strip_prefix(':')) {
+        (None, Some(dst)) => container.copy_to(Path::new(src), dst),
+        (Some(src), None) => container.copy_from(src, Path::new(dst)),
+        _ => Err(anyhow!(
+            "exactly one of source and destination must be in the container, prefixed with ':'"
+        )),
+    }
+}
+
+pub fn status(root: &str, config: &Option<String>, format: &Format) -> Result<()> {
+    let project = load_project(root, config)?;
+    let container: Container = Container::try_from(&project)?;
+    let status = Status::new(&project, &container)?;
+
+    match format {
+        Format::Human => print!("{}", status),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&status)?),
+    }
+
+    Ok(())
+}
+
+pub fn config_validate(root: &str, config: &Option<String>) -> Result<()> {
+    let project = load_project(root, config)?;
+    match project.config.warnings.len() {
+        0 => println!("{}: ok", project.config_file.display()),
+        n => println!("{}: ok, {} warning(s)", project.config_file.display(), n),
+    }
+    Ok(())
+}
+
+pub fn config_show(root: &str, config: &Option<String>, format: &show::Format) -> Result<()> {
+    let project = load_project(root, config)?;
+    let effective = Effective::new(&project, &config::Config::new()?);
+
+    match format {
+        show::Format::Json => println!("{}", serde_json::to_string_pretty(&effective)?),
+        show::Format::Toml => print!("{}", toml::to_string(&effective)?),
+    }
+
+    Ok(())
+}
+
+pub fn prune(yes: &bool, dry_run: &bool, older_than: &Option<Duration>) -> Result<()> {
+    let pruner = Pruner::new()?;
+    let created_before = match older_than {
+        Some(d) => Some(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)?
+                .saturating_sub(*d),
+        ),
+        None => None,
+    };
+    let prunables = pruner.find(created_before.map(|t| t.as_secs() as i64))?;
+
+    if prunables.is_empty() {
+        println!("nothing to prune");
+        return Ok(());
+    }
+
+    for p in &prunables {
+        println!(
+            "{:<10} {:<40} {:>10}  {}",
+            p.kind,
+            p.name,
+            format_size(p.size),
+            p.reason
+        );
+    }
+    let total = prunables.iter().map(|p| p.size).sum();
+    println!("reclaimable space: {}", format_size(total));
+
+    if *dry_run {
+        return Ok(());
+    }
+    if !*yes && !confirm(&format!("remove {} resources?", prunables.len()))? {
+        info!("aborted");
+        return Ok(());
+    }
+
+    pruner.remove(&prunables)?;
+    info!("removed {} resources", prunables.len());
+    Ok(())
+}
 
-    if *stop {
-        container.stop()?;
+pub fn reap(idle_timeout: &Option<Duration>) -> Result<()> {
+    let timeout = match idle_timeout {
+        Some(timeout) => *timeout,
+        None => config::Config::new()?.idle_timeout.ok_or_else(|| {
+            anyhow!("no idle timeout, pass --idle-timeout or set idle_timeout in the config")
+        })?,
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    for name in Reaper::new()?.reap(timeout, now)? {
+        info!("stopped {}", name);
     }
+    Ok(())
+}
 
+pub fn completions(shell: Shell, cmd: Command) -> Result<()> {
+    let bin = cmd.get_name().to_string();
+    let completer = std::env::current_exe()?;
+    complete::write_registration(shell, &bin, &completer.display().to_string(), &mut stdout())?;
     Ok(())
 }
 
+pub fn man(out_dir: &Option<PathBuf>, cmd: Command) -> Result<()> {
+    match out_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir).with_context(|| format!("failed to create {:?}", dir))?;
+            clap_mangen::generate_to(cmd, dir)?;
+            info!("man pages written to {}", dir.display());
+        }
+        None => clap_mangen::Man::new(cmd).render(&mut stdout())?,
+    }
+    Ok(())
+}
+
+fn format_size(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes.max(0) as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} {}", bytes.max(0), UNITS[0]),
+        _ => format!("{:.1} {}", size, UNITS[unit]),
+    }
+}
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+    use crate::{
+        docker::{fake::FakeDaemon, PROJECT_KEY, ROOT_KEY},
+        project::tests::TmpProjectDir,
+    };
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(999), "999 B");
+        assert_eq!(format_size(1500), "1.5 KB");
+        assert_eq!(format_size(2_300_000_000), "2.3 GB");
+    }
+
+    #[test]
+    fn test_shell() {
+        let json = r#"{
+            "dockerFile": "Dockerfile",
+            "postCreateCommand": ["make", "setup"],
+            "shell": "/bin/sh"
+        }"#;
+        let tmp_project = TmpProjectDir::new("aaa").devcontainer_json(json);
+        std::fs::write(
+            tmp_project.root.join(".devcontainer/Dockerfile"),
+            "FROM alpine",
+        )
+        .unwrap();
+        let root = tmp_project.root.display().to_string();
+        let daemon = FakeDaemon::start();
+        daemon.install();
+
+        shell(&root, &None, &None).unwrap();
+        let calls = daemon.cli_calls();
+        let commands: Vec<&str> = calls.iter().map(|c| c[0].as_str()).collect();
+        assert_eq!(commands, ["build", "run", "exec", "exec", "exec"]);
+        let project = Project::try_from(&tmp_project.root).unwrap();
+        let name = project.container_name.as_str();
+        let exec = |args: &[&str]| -> Vec<String> {
+            let exec = ["exec", "-it", "-w", "/workspaces/aaa", name];
+            exec.iter().chain(args).map(|a| a.to_string()).collect()
+        };
+        assert_eq!(
+            calls[2],
+            exec(&[
+                "sh",
+                "-c",
+                "command -v git >/dev/null || exit 0
+git config --global --add safe.directory /workspaces/aaa"
+            ])
+        );
+        assert_eq!(calls[3], exec(&["make", "setup"]));
+        assert_eq!(calls[4], exec(&["/bin/sh"]));
+        let containers = daemon.containers();
+        assert_eq!(containers.len(), 1);
+        assert_eq!(
+            containers[0].names,
+            Some(vec![format!("/{}", project.container_name)])
+        );
+        assert_eq!(containers[0].image, Some(project.image_tag()));
+        assert_eq!(containers[0].state.as_deref(), Some("exited"));
+
+        // the stopped container is started again, without building or running anything new
+        shell(&root, &None, &Some(false)).unwrap();
+        let calls = daemon.cli_calls();
+        assert_eq!(calls.len(), 6);
+        assert_eq!(calls[5], exec(&["/bin/sh"]));
+        assert_eq!(daemon.containers()[0].state.as_deref(), Some("running"));
+
+        // a shell exiting with the status of a failed command still stops the container
+        daemon.script_cli(3, "");
+        let err = shell(&root, &None, &None).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DevspaceError>(),
+            Some(DevspaceError::UserCommandFailed { code: Some(3), .. })
+        ));
+        assert_eq!(daemon.containers()[0].state.as_deref(), Some("exited"));
+    }
+
+    #[test]
+    fn test_prune() {
+        let tmp_project = TmpProjectDir::new("bbb");
+        let root = tmp_project.root.display().to_string();
+        let daemon = FakeDaemon::start();
+        daemon.install();
+        daemon.add_image("bbb:latest", &[(PROJECT_KEY, "0123"), (ROOT_KEY, &root)]);
+        daemon.add_image("gone:latest", &[(PROJECT_KEY, "4567"), (ROOT_KEY, "/gone")]);
+        daemon.add_container(
+            "bbb",
+            "bbb:latest",
+            &[(PROJECT_KEY, "0123"), (ROOT_KEY, &root)],
+            true,
+        );
+        daemon.add_container(
+            "gone",
+            "gone:latest",
+            &[(PROJECT_KEY, "4567"), (ROOT_KEY, "/gone")],
+            false,
+        );
+
+        prune(&true, &true, &None).unwrap();
+        assert_eq!(daemon.containers().len(), 2, "dry run removes nothing");
+
+        prune(&true, &false, &None).unwrap();
+        let containers = daemon.containers();
+        assert_eq!(containers.len(), 1);
+        assert_eq!(containers[0].names, Some(vec!["/bbb".to_string()]));
+        let images = daemon.images();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].repo_tags, ["bbb:latest"]);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{docker::all_containers, project::find_configs};
+use clap_complete::{
+    engine::{CompletionCandidate, PathCompleter, ValueCompleter},
+    env::Shells,
+    Shell,
+};
+use std::{
+    ffi::OsStr,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// environment variable switching the binary into completion mode
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+/// write the script registering the completions of `bin` for `shell`, the script calls
+/// `completer` to complete, so completions stay in sync with the installed binary
+pub fn write_registration(
+    shell: Shell,
+    bin: &str,
+    completer: &str,
+    out: &mut dyn Write,
+) -> std::io::Result<()> {
+    let name = shell.to_string();
+    let shells = Shells::builtins();
+    let shell = shells
+        .completer(&name)
+        .ok_or_else(|| std::io::Error::other(format!("unsupported shell {}", name)))?;
+    shell.write_registration(COMPLETE_VAR, bin, bin, completer, out)
+}
+
+/// names of the devcontainer configs of the project at the `--root` typed so far, or in the
+/// current directory
+pub fn config_names() -> Vec<CompletionCandidate> {
+    let root = typed_root(std::env::args())
+        .map(Ok)
+        .unwrap_or_else(std::env::current_dir);
+    let Ok(configs) = root
+        .map_err(anyhow::Error::from)
+        .and_then(|dir| find_configs(&dir))
+    else {
+        return vec![];
+    };
+    configs
+        .into_iter()
+        .filter_map(|c| {
+            let help = c.file.display().to_string();
+            c.name
+                .map(|name| CompletionCandidate::new(name).help(Some(help.into())))
+        })
+        .collect()
+}
+
+/// the last `--root` of the command line being completed
+fn typed_root(args: impl Iterator<Item = String>) -> Option<PathBuf> {
+    let mut root = None;
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        if arg == "--root" {
+            root = args.next_if(|a| !a.starts_with('-')).or(root);
+        } else if let Some(value) = arg.strip_prefix("--root=") {
+            root = Some(value.to_string());
+        }
+    }
+    root.filter(|r| !r.is_empty()).map(PathBuf::from)
+}
+
+/// completes `--root` with directories and the roots of existing devspace containers, the
+/// container name is shown as help
+#[derive(Debug, Clone, Copy)]
+pub struct RootCompleter;
+
+impl ValueCompleter for RootCompleter {
+    fn complete(&self, current: &OsStr) -> Vec<CompletionCandidate> {
+        let mut candidates = PathCompleter::dir().complete(current);
+        let current = current.to_string_lossy();
+        let containers = all_containers().unwrap_or_default();
+        candidates.extend(root_candidates(&containers, &current));
+        candidates
+    }
+}
+
+fn root_candidates(
+    containers: &[(String, impl AsRef<Path>)],
+    current: &str,
+) -> Vec<CompletionCandidate> {
+    containers
+        .iter()
+        .map(|(name, root)| (name, root.as_ref().display().to_string()))
+        .filter(|(_, root)| root.starts_with(current))
+        .map(|(name, root)| CompletionCandidate::new(root).help(Some(name.clone().into())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_root() {
+        let args = |line: &str| line.split(' ').map(String::from).collect::<Vec<_>>();
+        assert_eq!(
+            typed_root(args("devspace -- devspace shell").into_iter()),
+            None
+        );
+        assert_eq!(
+            typed_root(args("devspace -- devspace shell --root /src/aaa --config").into_iter()),
+            Some(PathBuf::from("/src/aaa"))
+        );
+        assert_eq!(
+            typed_root(args("devspace -- devspace --root=a shell --root b").into_iter()),
+            Some(PathBuf::from("b"))
+        );
+        assert_eq!(
+            typed_root(args("devspace -- devspace shell --root --config").into_iter()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_root_candidates() {
+        let containers = [
+            ("aaa-0123".to_string(), "/src/aaa"),
+            ("bbb-4567".to_string(), "/home/u/bbb"),
+        ];
+        let candidates = root_candidates(&containers, "/src");
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].get_value(), "/src/aaa");
+        assert_eq!(candidates[0].get_help().unwrap().to_string(), "aaa-0123");
+    }
+
+    #[test]
+    fn test_write_registration() {
+        for shell in [
+            Shell::Bash,
+            Shell::Zsh,
+            Shell::Fish,
+            Shell::PowerShell,
+            Shell::Elvish,
+        ] {
+            let mut out = vec![];
+            write_registration(shell, "devspace", "/usr/bin/devspace", &mut out).unwrap();
+            let script = String::from_utf8(out).unwrap();
+            assert!(
+                script.contains("/usr/bin/devspace"),
+                "{}: {}",
+                shell,
+                script
+            );
+        }
+    }
+}
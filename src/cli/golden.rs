@@ -0,0 +1,122 @@
+//! snapshot tests of the devcontainer configs in `testdata/fixtures`, every fixture is copied
+//! into the `.devcontainer` directory of a project and gets two snapshots:
+//!
+//! - `config.golden`, the effective config as shown by `config show` with its warnings, or the
+//!   error if the config is rejected
+//! - `calls.golden`, the docker cli calls of creating the container, opening a shell and
+//!   running a command in a throwaway container
+//!
+//! run with `UPDATE_GOLDEN=1` to accept changed snapshots
+
+use super::show::Effective;
+use crate::{
+    docker::{fake::FakeDaemon, Container, Ephemeral},
+    project::{tests::TmpProjectDir, Project, CONFIG_DIR, DEFAULT_SHELL},
+};
+use anyhow::Result;
+use std::{fs, path::Path};
+
+const CONFIG: &str = "config.golden";
+const CALLS: &str = "calls.golden";
+
+struct Snapshots {
+    config: String,
+    calls: String,
+}
+
+fn snapshots(fixture: &Path) -> Snapshots {
+    let name = fixture.file_name().unwrap().to_string_lossy();
+    let tmp_project = TmpProjectDir::new(&name);
+    for entry in fs::read_dir(fixture).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|e| e == "golden") {
+            continue;
+        }
+        let dst = tmp_project
+            .root
+            .join(CONFIG_DIR)
+            .join(path.file_name().unwrap());
+        fs::copy(&path, dst).unwrap();
+    }
+
+    let root = tmp_project.root.display().to_string();
+    let project = match Project::try_from(&tmp_project.root) {
+        Ok(project) => project,
+        Err(e) => {
+            return Snapshots {
+                config: format!("error: {:#}\n", e).replace(&root, "$ROOT"),
+                calls: String::new(),
+            }
+        }
+    };
+    let daemon = FakeDaemon::start();
+    daemon.install();
+    run(&project).unwrap();
+
+    // leave out what depends on the temporary directory, the environment or the exact
+    // config content
+    let normalize = |s: String| {
+        s.replace(&root, "$ROOT")
+            .replace(&project.config.hash, "$HASH")
+            .replace(&project.image_tag(), "$IMAGE")
+            .replace(&project.id, "$ID")
+            .replace(&project.container_name, "$CONTAINER")
+    };
+    let mut effective =
+        serde_json::to_value(Effective::new(&project, &Default::default())).unwrap();
+    effective.as_object_mut().unwrap().remove("runtime");
+    let mut config = serde_json::to_string_pretty(&effective).unwrap();
+    config.push('\n');
+    for warning in &project.config.warnings {
+        config.push_str(&format!("warning: {}\n", warning));
+    }
+    Snapshots {
+        config: normalize(config),
+        calls: normalize(daemon.cli_transcript()),
+    }
+}
+
+fn run(project: &Project) -> Result<()> {
+    let shell = project.config.shell.as_deref().unwrap_or(DEFAULT_SHELL);
+    let container = Container::try_from(project)?;
+    container.setup()?;
+    container.exec(&[shell])?;
+    container.stop()?;
+    Ephemeral::new(project)?.run(&["make", "test"], false)
+}
+
+fn check(golden: &Path, actual: &str, update: bool) {
+    if update {
+        match actual.is_empty() {
+            true => {
+                let _ = fs::remove_file(golden);
+            }
+            false => fs::write(golden, actual).unwrap(),
+        }
+        return;
+    }
+    let expected = fs::read_to_string(golden).unwrap_or_default();
+    assert_eq!(
+        actual,
+        expected,
+        "{} differs, run with UPDATE_GOLDEN=1 to accept the change",
+        golden.display()
+    );
+}
+
+#[test]
+fn test_fixtures() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/fixtures");
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut fixtures: Vec<_> = fs::read_dir(fixtures)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    fixtures.sort();
+
+    for fixture in fixtures {
+        let snapshots = snapshots(&fixture);
+        check(&fixture.join(CONFIG), &snapshots.config, update);
+        check(&fixture.join(CALLS), &snapshots.calls, update);
+    }
+}
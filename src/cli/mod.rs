@@ -1,8 +1,23 @@
 mod command;
+mod complete;
+#[cfg(test)]
+mod golden;
+mod prompt;
+mod show;
+mod status;
 
+use crate::{
+    config::parse_duration,
+    docker::{self, LogOptions},
+    logging::{self, LogFormat},
+};
 use anyhow::Result;
-use clap::Parser;
-use clap::Subcommand;
+use clap::{ArgAction, CommandFactory, Parser, Subcommand};
+use clap_complete::{ArgValueCandidates, ArgValueCompleter, CompleteEnv, Shell};
+use complete::{RootCompleter, COMPLETE_VAR};
+use status::Format;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -11,27 +26,307 @@ pub struct Cli {
     #[command(subcommand)]
     pub cmds: Commands,
 
-    /// project root, default "."
-    #[arg(long, global = true, default_value = ".")]
+    /// project root or any directory below it, default "."
+    #[arg(long, global = true, default_value = ".", add = ArgValueCompleter::new(RootCompleter))]
     root: String,
+
+    /// name of the devcontainer config, or its path relative to the project root
+    #[arg(long, global = true, add = ArgValueCandidates::new(complete::config_names))]
+    config: Option<String>,
+
+    /// show more, -v adds debug messages and the docker commands run, -vv everything
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// only show warnings and errors
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// format of the messages on stderr
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// print the docker commands that would run instead of running them
+    #[arg(long, global = true)]
+    dry_run: bool,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
-    Shell {
+    /// write a devcontainer config for the project, based on the detected project type
+    Init {
         #[arg(from_global)]
         root: String,
 
-        /// stop container after shell exits
+        /// template to use instead of the detected one, builtin or from the user templates
         #[arg(short, long)]
+        template: Option<String>,
+
+        /// overwrite existing files
+        #[arg(long)]
+        force: bool,
+    },
+    Shell {
+        #[arg(from_global)]
+        root: String,
+
+        #[arg(from_global)]
+        config: Option<String>,
+
+        /// stop the container after the last shell exits, overriding shutdownAction
+        #[arg(short, long, overrides_with = "no_stop")]
         stop: bool,
+
+        /// keep the container running after the last shell exits, overriding shutdownAction,
+        /// which stops it unless set to "none"
+        #[arg(long, overrides_with = "stop")]
+        no_stop: bool,
+    },
+    /// run a command in a new container from the project image, removed when it exits
+    Run {
+        #[arg(from_global)]
+        root: String,
+
+        #[arg(from_global)]
+        config: Option<String>,
+
+        /// command and its arguments
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        cmd: Vec<String>,
+    },
+    /// show the output of the project container
+    Logs {
+        #[arg(from_global)]
+        root: String,
+
+        #[arg(from_global)]
+        config: Option<String>,
+
+        /// keep streaming new output
+        #[arg(short, long)]
+        follow: bool,
+
+        /// only show output newer than this, a duration like 10m or an RFC 3339 time
+        #[arg(long, value_parser = parse_since)]
+        since: Option<i64>,
+
+        /// number of lines to show from the end
+        #[arg(short = 'n', long)]
+        tail: Option<usize>,
+
+        /// show timestamps
+        #[arg(short, long)]
+        timestamps: bool,
+
+        /// show the output of the lifecycle commands run by devspace instead
+        #[arg(long, conflicts_with_all = ["follow", "since", "timestamps"])]
+        lifecycle: bool,
+    },
+    /// copy files between the host and the project container, prefix the container side
+    /// with ':', e.g. `devspace cp :target/report.html .`
+    Cp {
+        #[arg(from_global)]
+        root: String,
+
+        #[arg(from_global)]
+        config: Option<String>,
+
+        /// file or directory to copy
+        src: String,
+
+        /// destination, an existing directory receives a copy of the source
+        dst: String,
+    },
+    /// show container, image and lifecycle status of the project
+    Status {
+        #[arg(from_global)]
+        root: String,
+
+        #[arg(from_global)]
+        config: Option<String>,
+
+        /// output format
+        #[arg(short, long, value_enum, default_value_t = Format::Human)]
+        format: Format,
+    },
+    /// inspect the devcontainer config
+    Config {
+        #[command(subcommand)]
+        cmd: ConfigCommands,
+    },
+    /// remove containers and images of deleted projects and untagged images
+    Prune {
+        /// remove without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+
+        /// only show what would be removed
+        #[arg(from_global)]
+        dry_run: bool,
+
+        /// only remove resources older than this, e.g. 12h, 30d, 2w
+        #[arg(long, value_parser = parse_duration)]
+        older_than: Option<Duration>,
+    },
+    /// stop containers without shells attached for longer than the idle timeout, meant to run
+    /// from cron or a systemd timer
+    Reap {
+        /// stop containers idle for longer than this instead of the configured idle_timeout,
+        /// e.g. 30m, 2h
+        #[arg(long, value_parser = parse_duration)]
+        idle_timeout: Option<Duration>,
+    },
+    /// print the shell code enabling completions, e.g. `source <(devspace completions bash)`
+    Completions { shell: Shell },
+    /// write man pages for devspace and its subcommands
+    Man {
+        /// directory to write the pages to, prints the devspace page when not given
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// check the config for errors and unknown keys, exits non-zero on errors
+    Validate {
+        #[arg(from_global)]
+        root: String,
+
+        #[arg(from_global)]
+        config: Option<String>,
+    },
+    /// print the effective config after defaults and variable substitution, with the
+    /// source of every value
+    Show {
+        #[arg(from_global)]
+        root: String,
+
+        #[arg(from_global)]
+        config: Option<String>,
+
+        /// output format
+        #[arg(short, long, value_enum, default_value_t = show::Format::Json)]
+        format: show::Format,
     },
 }
 
 pub fn run() -> Result<()> {
+    CompleteEnv::with_factory(Cli::command)
+        .var(COMPLETE_VAR)
+        .complete();
     let root_cmd = Cli::parse();
+    logging::init(root_cmd.verbose, root_cmd.quiet, root_cmd.log_format);
+    docker::set_dry_run(root_cmd.dry_run);
 
     match &root_cmd.cmds {
-        Commands::Shell { root, stop } => command::shell(root, stop),
+        Commands::Init {
+            root,
+            template,
+            force,
+        } => command::init(root, template, force),
+        Commands::Shell {
+            root,
+            config,
+            stop,
+            no_stop,
+        } => {
+            let stop = match (stop, no_stop) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            };
+            command::shell(root, config, &stop)
+        }
+        Commands::Run { root, config, cmd } => command::run(root, config, cmd),
+        Commands::Logs {
+            root,
+            config,
+            follow,
+            since,
+            tail,
+            timestamps,
+            lifecycle,
+        } => {
+            let options = LogOptions {
+                follow: *follow,
+                since: *since,
+                tail: *tail,
+                timestamps: *timestamps,
+            };
+            command::logs(root, config, &options, lifecycle)
+        }
+        Commands::Cp {
+            root,
+            config,
+            src,
+            dst,
+        } => command::cp(root, config, src, dst),
+        Commands::Status {
+            root,
+            config,
+            format,
+        } => command::status(root, config, format),
+        Commands::Config { cmd } => match cmd {
+            ConfigCommands::Validate { root, config } => command::config_validate(root, config),
+            ConfigCommands::Show {
+                root,
+                config,
+                format,
+            } => command::config_show(root, config, format),
+        },
+        Commands::Prune {
+            yes,
+            dry_run,
+            older_than,
+        } => command::prune(yes, dry_run, older_than),
+        Commands::Reap { idle_timeout } => command::reap(idle_timeout),
+        Commands::Completions { shell } => command::completions(*shell, Cli::command()),
+        Commands::Man { out_dir } => command::man(out_dir, Cli::command()),
+    }
+}
+
+/// a duration before now or an RFC 3339 time, as unix timestamp
+fn parse_since(s: &str) -> Result<i64, String> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Ok(time.timestamp());
+    }
+    let duration = parse_duration(s).map_err(|_| {
+        format!(
+            "invalid time {:?}, expected a duration like 10m or an RFC 3339 time",
+            s
+        )
+    })?;
+    Ok(chrono::Utc::now().timestamp() - duration.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_stop_flags() {
+        let stop = |args: &[&str]| match Cli::parse_from(args).cmds {
+            Commands::Shell { stop, no_stop, .. } => (stop, no_stop),
+            _ => unreachable!(),
+        };
+        assert_eq!(stop(&["devspace", "shell"]), (false, false));
+        assert_eq!(stop(&["devspace", "shell", "--stop"]), (true, false));
+        assert_eq!(
+            stop(&["devspace", "shell", "--stop", "--no-stop"]),
+            (false, true)
+        );
+        assert_eq!(
+            stop(&["devspace", "shell", "--no-stop", "-s"]),
+            (true, false)
+        );
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("2024-01-01T00:00:00Z").unwrap(), 1704067200);
+        let since = parse_since("10m").unwrap();
+        assert!((chrono::Utc::now().timestamp() - 600 - since).abs() <= 1);
+        assert!(parse_since("yesterday").is_err());
     }
 }
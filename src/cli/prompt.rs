@@ -0,0 +1,82 @@
+use anyhow::{anyhow, Result};
+use std::io::{self, BufRead, Write};
+
+/// ask a yes/no question on the terminal, anything but "y" or "yes" means no
+pub fn confirm(question: &str) -> Result<bool> {
+    confirm_with(question, &mut io::stdin().lock(), &mut io::stdout())
+}
+
+fn confirm_with(question: &str, input: &mut impl BufRead, output: &mut impl Write) -> Result<bool> {
+    write!(output, "{} [y/N] ", question)?;
+    output.flush()?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// ask to pick one of the options on the terminal, returns the index of the chosen option
+pub fn select(question: &str, options: &[String]) -> Result<usize> {
+    select_with(
+        question,
+        options,
+        &mut io::stdin().lock(),
+        &mut io::stdout(),
+    )
+}
+
+fn select_with(
+    question: &str,
+    options: &[String],
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<usize> {
+    writeln!(output, "{}", question)?;
+    for (i, option) in options.iter().enumerate() {
+        writeln!(output, "  {}) {}", i + 1, option)?;
+    }
+
+    loop {
+        write!(output, "choose [1-{}]: ", options.len())?;
+        output.flush()?;
+
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            return Err(anyhow!("no option selected"));
+        }
+        match answer.trim().parse::<usize>() {
+            Ok(n) if (1..=options.len()).contains(&n) => return Ok(n - 1),
+            _ => writeln!(output, "invalid choice {:?}", answer.trim())?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirm() {
+        let mut output = Vec::new();
+        assert!(confirm_with("remove?", &mut "y\n".as_bytes(), &mut output).unwrap());
+        assert_eq!(String::from_utf8(output).unwrap(), "remove? [y/N] ");
+
+        assert!(confirm_with("remove?", &mut "YES\n".as_bytes(), &mut Vec::new()).unwrap());
+        assert!(!confirm_with("remove?", &mut "\n".as_bytes(), &mut Vec::new()).unwrap());
+        assert!(!confirm_with("remove?", &mut "".as_bytes(), &mut Vec::new()).unwrap());
+    }
+
+    #[test]
+    fn test_select() {
+        let options = vec!["rust".to_string(), "docs".to_string()];
+        let mut output = Vec::new();
+        let choice = select_with("which?", &options, &mut "3\nx\n2\n".as_bytes(), &mut output);
+        assert_eq!(choice.unwrap(), 1);
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("which?\n  1) rust\n  2) docs\n"));
+        assert!(output.contains("invalid choice \"3\""));
+
+        assert!(select_with("which?", &options, &mut "".as_bytes(), &mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,262 @@
+use crate::{
+    config,
+    docker::{find_docker_cli, DockerSource},
+    project::{step_name, ImageSource, Project, ShutdownAction, DEFAULT_SHELL},
+};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{collections::BTreeMap, path::PathBuf};
+
+const DOCKER_HOST: &str = "DOCKER_HOST";
+const DEFAULT_DOCKER_HOST: &str = "unix:///var/run/docker.sock";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+/// where a value of the effective config comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// built into devspace
+    Default,
+    /// the devcontainer.json of the project, after variable substitution
+    Devcontainer,
+    /// the global config.toml
+    Global,
+    /// the environment of devspace
+    Env,
+    /// computed from the project location or other values
+    Derived,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Sourced<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+fn sourced<T>(value: T, source: Source) -> Sourced<T> {
+    Sourced { value, source }
+}
+
+/// what devspace will use for the project, with the source of every value
+#[derive(Debug, Serialize)]
+pub struct Effective {
+    pub name: Sourced<String>,
+    pub id: Sourced<String>,
+    pub root: Sourced<PathBuf>,
+    pub config_file: Sourced<PathBuf>,
+    pub container_name: Sourced<String>,
+    pub shell: Sourced<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dotfiles: Option<Sourced<String>>,
+    pub image: EffectiveImage,
+    pub workspace_folder: Sourced<String>,
+    pub workspace_mount: Sourced<String>,
+    pub shutdown_action: Sourced<ShutdownAction>,
+    pub mounts: Vec<Sourced<String>>,
+    pub container_env: BTreeMap<String, Sourced<String>>,
+    /// variables set to null in the config are left out, they are not passed to commands
+    pub remote_env: BTreeMap<String, Sourced<String>>,
+    pub lifecycle: BTreeMap<String, Sourced<Vec<String>>>,
+    pub runtime: EffectiveRuntime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EffectiveImage {
+    pub name: Sourced<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dockerfile: Option<Sourced<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<Sourced<PathBuf>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EffectiveRuntime {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cli: Option<Sourced<PathBuf>>,
+    pub host: Sourced<String>,
+}
+
+impl Effective {
+    pub fn new(project: &Project, global: &config::Config) -> Self {
+        let config = &project.config;
+        let from_config = |set: bool| {
+            if set {
+                Source::Devcontainer
+            } else {
+                Source::Default
+            }
+        };
+
+        let image = match &config.image_source {
+            ImageSource::Image(image) => EffectiveImage {
+                name: sourced(image.clone(), Source::Devcontainer),
+                dockerfile: None,
+                context: None,
+            },
+            ImageSource::Dockerfile(dockerfile) => EffectiveImage {
+                name: sourced(project.image_tag(), Source::Derived),
+                dockerfile: Some(sourced(dockerfile.clone(), Source::Devcontainer)),
+                context: Some(sourced(project.root.clone(), Source::Default)),
+            },
+        };
+
+        let mut lifecycle = BTreeMap::new();
+        if let Some(command) = &config.post_create_command {
+            for (name, args) in command.commands() {
+                lifecycle.insert(
+                    step_name("postCreateCommand", name),
+                    sourced(
+                        args.iter().map(|a| a.to_string()).collect(),
+                        Source::Devcontainer,
+                    ),
+                );
+            }
+        }
+
+        let host = match std::env::var(DOCKER_HOST) {
+            Ok(host) => sourced(host, Source::Env),
+            Err(_) => sourced(DEFAULT_DOCKER_HOST.to_string(), Source::Default),
+        };
+
+        Effective {
+            name: sourced(
+                project.name.clone(),
+                match config.name {
+                    Some(_) => Source::Devcontainer,
+                    None => Source::Derived,
+                },
+            ),
+            id: sourced(project.id.clone(), Source::Derived),
+            root: sourced(project.root.clone(), Source::Derived),
+            config_file: sourced(project.config_file.clone(), Source::Derived),
+            container_name: sourced(project.container_name.clone(), Source::Derived),
+            shell: shell(project, global),
+            dotfiles: global.dotfiles.clone().map(|d| sourced(d, Source::Global)),
+            image,
+            workspace_folder: sourced(
+                project.workspace_folder.clone(),
+                from_config(config.workspace_folder.is_some()),
+            ),
+            workspace_mount: sourced(
+                project.workspace_mount.clone(),
+                from_config(config.workspace_mount.is_some()),
+            ),
+            shutdown_action: sourced(
+                config.shutdown_action.unwrap_or_default(),
+                from_config(config.shutdown_action.is_some()),
+            ),
+            mounts: config
+                .mounts
+                .iter()
+                .map(|m| sourced(m.to_string(), Source::Devcontainer))
+                .collect(),
+            container_env: config
+                .container_env
+                .iter()
+                .map(|(k, v)| (k.clone(), sourced(v.clone(), Source::Devcontainer)))
+                .collect(),
+            remote_env: config
+                .remote_env
+                .iter()
+                .filter_map(|(k, v)| Some((k.clone(), sourced(v.clone()?, Source::Devcontainer))))
+                .collect(),
+            lifecycle,
+            runtime: EffectiveRuntime {
+                cli: docker_cli(global),
+                host,
+            },
+        }
+    }
+}
+
+/// the docker executable devspace runs, None if it is not found
+fn docker_cli(global: &config::Config) -> Option<Sourced<PathBuf>> {
+    let (path, source) = find_docker_cli(global.docker.clone()).ok()?;
+    let source = match source {
+        DockerSource::Env => Source::Env,
+        DockerSource::Global => Source::Global,
+        DockerSource::Default => Source::Default,
+    };
+    Some(sourced(path, source))
+}
+
+/// the shell of the project config, else the one of the global config, else the default
+pub fn shell(project: &Project, global: &config::Config) -> Sourced<String> {
+    match (&project.config.shell, &global.shell) {
+        (Some(shell), _) => sourced(shell.clone(), Source::Devcontainer),
+        (None, Some(shell)) => sourced(shell.clone(), Source::Global),
+        (None, None) => sourced(DEFAULT_SHELL.to_string(), Source::Default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::tests::TmpProjectDir;
+
+    #[test]
+    fn test_effective() {
+        let json = r#"{
+            "dockerFile": "Dockerfile",
+            "workspaceFolder": "/src/${localWorkspaceFolderBasename}",
+            "mounts": ["type=volume,source=cache-${devcontainerId},target=/cache"],
+            "containerEnv": { "A": "1" },
+            "remoteEnv": { "B": "${containerWorkspaceFolder}", "C": null },
+            "postCreateCommand": ["make"]
+        }"#;
+        let tmp_project = TmpProjectDir::new("aaa").devcontainer_json(json);
+        let project = Project::try_from(&tmp_project.root).unwrap();
+        let global = config::Config {
+            shell: Some("/bin/bash".to_string()),
+            docker: Some("sh".to_string()),
+            ..Default::default()
+        };
+
+        let effective = Effective::new(&project, &global);
+        assert_eq!(effective.name.source, Source::Derived);
+        assert_eq!(effective.shell.value, "/bin/bash");
+        assert_eq!(effective.shell.source, Source::Global);
+        let cli = effective.runtime.cli.as_ref().unwrap();
+        assert!(cli.value.ends_with("sh"));
+        assert_eq!(cli.source, Source::Global);
+        assert_eq!(effective.image.name.value, project.image_tag());
+        let dockerfile = effective.image.dockerfile.as_ref().unwrap();
+        assert!(dockerfile
+            .value
+            .starts_with(&tmp_project.root.display().to_string()));
+        assert_eq!(effective.workspace_folder.value, "/src/aaa");
+        assert_eq!(effective.workspace_folder.source, Source::Devcontainer);
+        assert_eq!(effective.workspace_mount.source, Source::Default);
+        assert_eq!(
+            effective.mounts[0].value,
+            format!("type=volume,source=cache-{},target=/cache", project.id)
+        );
+        assert_eq!(effective.remote_env["B"].value, "/src/aaa");
+        assert!(!effective.remote_env.contains_key("C"));
+        assert_eq!(effective.lifecycle["postCreateCommand"].value, ["make"]);
+
+        let json = serde_json::to_value(&effective).unwrap();
+        assert_eq!(json["shell"]["source"], "global");
+        assert_eq!(json["container_env"]["A"]["source"], "devcontainer");
+        assert!(toml::to_string(&effective).is_ok());
+    }
+
+    #[test]
+    fn test_shell() {
+        let mut project = Project::default();
+        let global = config::Config::default();
+        assert_eq!(shell(&project, &global).value, DEFAULT_SHELL);
+
+        project.config.shell = Some("/bin/fish".to_string());
+        let s = shell(&project, &global);
+        assert_eq!(
+            (s.value.as_str(), s.source),
+            ("/bin/fish", Source::Devcontainer)
+        );
+    }
+}
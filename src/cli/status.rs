@@ -0,0 +1,290 @@
+use crate::{
+    docker::Container,
+    project::{ImageSource, Project},
+};
+use anyhow::Result;
+use bollard::models::{MountPoint, Port};
+use chrono::{Local, TimeZone};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Human,
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Status {
+    pub project: String,
+    pub id: String,
+    pub root: PathBuf,
+    pub config_file: PathBuf,
+    pub container: ContainerStatus,
+    pub image: ImageStatus,
+    /// None when there is no container or it was created without a config hash
+    pub config_drift: Option<bool>,
+    pub stale: bool,
+    pub ports: Vec<String>,
+    pub mounts: Vec<String>,
+    pub lifecycle: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContainerStatus {
+    pub state: String,
+    pub status: Option<String>,
+    pub id: Option<String>,
+    pub created: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImageStatus {
+    pub name: String,
+    pub managed: bool,
+    pub existing: bool,
+    pub id: Option<String>,
+    pub created: Option<i64>,
+}
+
+impl Status {
+    pub fn new(project: &Project, container: &Container) -> Result<Self> {
+        let image = container.image()?;
+        let image_summary = image.summary();
+        let summary = container.summary();
+
+        let container_status = ContainerStatus {
+            state: summary
+                .and_then(|s| s.state.clone())
+                .unwrap_or_else(|| "not created".to_string()),
+            status: summary.and_then(|s| s.status.clone()),
+            id: summary.and_then(|s| s.id.clone()),
+            created: summary.and_then(|s| s.created),
+        };
+        let image_status = ImageStatus {
+            name: image.name().to_string(),
+            managed: matches!(project.config.image_source, ImageSource::Dockerfile(_)),
+            existing: image.existing(),
+            id: image_summary.map(|s| s.id.clone()),
+            created: image_summary.map(|s| s.created),
+        };
+
+        let config_drift = container.config_drift();
+        let outdated_container = match (summary.and_then(|s| s.image_id.as_ref()), image_summary) {
+            (Some(used), Some(current)) => used != &current.id,
+            _ => false,
+        };
+        let outdated_image = match (&project.config.image_source, image_summary) {
+            (ImageSource::Dockerfile(dockerfile), Some(current)) => {
+                modified_since(Path::new(dockerfile), current.created)
+            }
+            _ => false,
+        };
+
+        Ok(Status {
+            project: project.name.clone(),
+            id: project.id.clone(),
+            root: project.root.clone(),
+            config_file: project.config_file.clone(),
+            container: container_status,
+            image: image_status,
+            config_drift,
+            stale: config_drift == Some(true) || outdated_container || outdated_image,
+            ports: summary
+                .and_then(|s| s.ports.as_ref())
+                .map(|ports| ports.iter().map(format_port).collect())
+                .unwrap_or_default(),
+            mounts: summary
+                .and_then(|s| s.mounts.as_ref())
+                .map(|mounts| mounts.iter().map(format_mount).collect())
+                .unwrap_or_default(),
+            lifecycle: match summary {
+                Some(_) => container.state()?.lifecycle,
+                None => vec![],
+            },
+        })
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "project:      {}", self.project)?;
+        writeln!(f, "id:           {}", self.id)?;
+        writeln!(f, "root:         {}", self.root.display())?;
+        writeln!(f, "config:       {}", self.config_file.display())?;
+        match &self.container.status {
+            Some(status) => writeln!(f, "container:    {} ({})", self.container.state, status)?,
+            None => writeln!(f, "container:    {}", self.container.state)?,
+        }
+        let built = match (self.image.managed, self.image.created) {
+            (false, _) => "external".to_string(),
+            (true, Some(created)) => format!("built {}", format_time(created)),
+            (true, None) => "not built".to_string(),
+        };
+        writeln!(f, "image:        {} ({})", self.image.name, built)?;
+        let drift = match self.config_drift {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "unknown",
+        };
+        writeln!(f, "config drift: {}", drift)?;
+        writeln!(f, "stale:        {}", if self.stale { "yes" } else { "no" })?;
+        writeln!(f, "ports:        {}", join_or_dash(&self.ports))?;
+        writeln!(f, "mounts:       {}", join_or_dash(&self.mounts))?;
+        writeln!(f, "lifecycle:    {}", join_or_dash(&self.lifecycle))
+    }
+}
+
+fn join_or_dash(items: &[String]) -> String {
+    if items.is_empty() {
+        "-".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+fn format_time(timestamp: i64) -> String {
+    match Local.timestamp_opt(timestamp, 0).single() {
+        Some(t) => t.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => timestamp.to_string(),
+    }
+}
+
+fn format_port(port: &Port) -> String {
+    let typ = port
+        .typ
+        .map(|t| t.to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| "tcp".to_string());
+    match port.public_port {
+        Some(public) => format!(
+            "{}:{}->{}/{}",
+            port.ip.as_deref().unwrap_or("0.0.0.0"),
+            public,
+            port.private_port,
+            typ
+        ),
+        None => format!("{}/{}", port.private_port, typ),
+    }
+}
+
+fn format_mount(mount: &MountPoint) -> String {
+    let source = mount
+        .name
+        .as_ref()
+        .or(mount.source.as_ref())
+        .map(|s| s.as_str())
+        .unwrap_or_default();
+    let destination = mount.destination.as_deref().unwrap_or_default();
+    match mount.rw {
+        Some(false) => format!("{} -> {} (ro)", source, destination),
+        _ => format!("{} -> {}", source, destination),
+    }
+}
+
+/// whether the file was modified after the given unix timestamp
+fn modified_since(path: &Path, timestamp: i64) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .is_some_and(|d| d.as_secs() as i64 > timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bollard::models::{MountPointTypeEnum, PortTypeEnum};
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_format_port() {
+        let published = Port {
+            ip: Some("127.0.0.1".to_string()),
+            private_port: 80,
+            public_port: Some(8080),
+            typ: Some(PortTypeEnum::TCP),
+        };
+        assert_eq!(format_port(&published), "127.0.0.1:8080->80/tcp");
+
+        let exposed = Port {
+            private_port: 53,
+            typ: Some(PortTypeEnum::UDP),
+            ..Default::default()
+        };
+        assert_eq!(format_port(&exposed), "53/udp");
+    }
+
+    #[test]
+    fn test_format_mount() {
+        let bind = MountPoint {
+            typ: Some(MountPointTypeEnum::BIND),
+            source: Some("/home/user/project".to_string()),
+            destination: Some("/workspace".to_string()),
+            rw: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(format_mount(&bind), "/home/user/project -> /workspace");
+
+        let volume = MountPoint {
+            typ: Some(MountPointTypeEnum::VOLUME),
+            name: Some("cache".to_string()),
+            source: Some("/var/lib/docker/volumes/cache/_data".to_string()),
+            destination: Some("/cache".to_string()),
+            rw: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(format_mount(&volume), "cache -> /cache (ro)");
+    }
+
+    #[test]
+    fn test_modified_since() {
+        let f = NamedTempFile::new().unwrap();
+        assert!(modified_since(f.path(), 0));
+        assert!(!modified_since(f.path(), i64::MAX));
+        assert!(!modified_since(Path::new("/not/existing"), 0));
+    }
+
+    #[test]
+    fn test_display_not_created() {
+        let status = Status {
+            project: "aaa".to_string(),
+            id: "0123456789ab".to_string(),
+            root: PathBuf::from("/src/aaa"),
+            config_file: PathBuf::from("/src/aaa/.devcontainer/devcontainer.json"),
+            container: ContainerStatus {
+                state: "not created".to_string(),
+                status: None,
+                id: None,
+                created: None,
+            },
+            image: ImageStatus {
+                name: "aaa".to_string(),
+                managed: true,
+                existing: false,
+                id: None,
+                created: None,
+            },
+            config_drift: None,
+            stale: false,
+            ports: vec![],
+            mounts: vec![],
+            lifecycle: vec![],
+        };
+
+        let output = status.to_string();
+        assert!(output.contains("container:    not created\n"));
+        assert!(output.contains("image:        aaa (not built)\n"));
+        assert!(output.contains("config drift: unknown\n"));
+        assert!(output.contains("ports:        -\n"));
+
+        let json: serde_json::Value = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["container"]["state"], "not created");
+        assert_eq!(json["config_drift"], serde_json::Value::Null);
+    }
+}
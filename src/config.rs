@@ -1,16 +1,83 @@
 use anyhow::Result;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+use std::{path::PathBuf, time::Duration};
 
 const PREFIX: &str = "devspace";
 const CONFIG: &str = "config.toml";
+const TEMPLATES: &str = "templates";
 
 #[derive(Debug, Deserialize, Default)]
 pub struct Config {
     pub dotfiles: Option<String>,
+    /// shell for projects that do not configure one
+    pub shell: Option<String>,
+    /// docker executable, a name looked up in PATH or a path
+    pub docker: Option<String>,
+    /// how the ssh agent of the host is made available in containers
+    #[serde(default)]
+    pub forward_ssh_agent: Forwarding,
+    /// copy the git identity and settings of the host into new containers, on by default
+    pub share_git_config: Option<bool>,
+    /// answer `git credential` requests in containers with the credential helpers of the host
+    #[serde(default)]
+    pub forward_git_credentials: bool,
+    /// forward the gpg agent and public keys of the host, for signing commits in containers
+    #[serde(default)]
+    pub forward_gpg_agent: bool,
+    /// how long containers may run without a shell before `devspace reap` stops them, e.g. 2h
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub idle_timeout: Option<Duration>,
+    /// refuse to create containers on docker hosts short of the hostRequirements of the
+    /// project, instead of warning
+    #[serde(default)]
+    pub enforce_host_requirements: bool,
+}
+
+/// way a host agent socket reaches a container, `true` and `false` stand for mount and off
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(from = "RawForwarding")]
+pub enum Forwarding {
+    /// bind mount a directory the agent of each devspace process is proxied to, or the
+    /// docker desktop agent socket on macOS
+    #[default]
+    Mount,
+    /// relay the agent over `docker exec` for runtimes that can not mount host sockets
+    Relay,
+    Off,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawForwarding {
+    Enabled(bool),
+    Mode(Mode),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Mode {
+    Mount,
+    Relay,
+    Off,
+}
+
+impl From<RawForwarding> for Forwarding {
+    fn from(raw: RawForwarding) -> Self {
+        match raw {
+            RawForwarding::Enabled(true) | RawForwarding::Mode(Mode::Mount) => Forwarding::Mount,
+            RawForwarding::Mode(Mode::Relay) => Forwarding::Relay,
+            RawForwarding::Enabled(false) | RawForwarding::Mode(Mode::Off) => Forwarding::Off,
+        }
+    }
 }
 
 impl Config {
     pub fn new() -> Result<Self> {
+        // commands tested against the fake docker do not see the config of the host
+        #[cfg(test)]
+        if crate::docker::fake::installed().is_some() {
+            return Ok(Default::default());
+        }
         let xdg = xdg::BaseDirectories::with_prefix(PREFIX)?;
         if let Some(f) = xdg.find_config_file(CONFIG) {
             let toml = std::fs::read_to_string(f)?;
@@ -19,11 +86,47 @@ impl Config {
         Ok(Default::default())
     }
 
+    /// directory of the user templates for `devspace init`
+    pub fn templates_dir() -> Result<PathBuf> {
+        let xdg = xdg::BaseDirectories::with_prefix(PREFIX)?;
+        Ok(xdg.get_config_home().join(TEMPLATES))
+    }
+
     fn from_str(toml: &str) -> Result<Self> {
         Ok(toml::from_str(toml)?)
     }
 }
 
+/// a duration like 30s, 10m, 12h, 30d or 2w
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "invalid duration {:?}, expected e.g. 30s, 10m, 12h, 30d, 2w",
+            s
+        )
+    };
+    let unit_start = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (value, unit) = s.split_at(unit_start);
+    let value: u64 = value.parse().map_err(|_| invalid())?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    let secs = value.checked_mul(seconds).ok_or_else(invalid)?;
+    Ok(Duration::from_secs(secs))
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+    let s = String::deserialize(d)?;
+    parse_duration(&s)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -32,11 +135,52 @@ mod tests {
     fn test_config() {
         let toml = r#"
         dotfiles = "uuuuuuu"
+        shell = "/bin/bash"
+        docker = "podman"
+        forward_ssh_agent = "relay"
+        share_git_config = false
+        forward_git_credentials = true
+        forward_gpg_agent = true
+        idle_timeout = "2h"
+        enforce_host_requirements = true
         "#;
         let config = Config::from_str(toml).unwrap();
         assert_eq!(config.dotfiles, Some("uuuuuuu".to_string()));
+        assert_eq!(config.shell, Some("/bin/bash".to_string()));
+        assert_eq!(config.docker, Some("podman".to_string()));
+        assert_eq!(config.forward_ssh_agent, Forwarding::Relay);
+        assert_eq!(config.share_git_config, Some(false));
+        assert!(config.forward_git_credentials);
+        assert!(config.forward_gpg_agent);
+        assert_eq!(config.idle_timeout, Some(Duration::from_secs(2 * 3600)));
+        assert!(config.enforce_host_requirements);
 
         let config = Config::from_str("").unwrap();
         assert_eq!(config.dotfiles, None);
+        assert_eq!(config.forward_ssh_agent, Forwarding::Mount);
+
+        let config = Config::from_str("forward_ssh_agent = false").unwrap();
+        assert_eq!(config.forward_ssh_agent, Forwarding::Off);
+        assert!(Config::from_str(r#"forward_ssh_agent = "tcp""#).is_err());
+        let err = Config::from_str(r#"idle_timeout = "2 hours""#).unwrap_err();
+        assert!(err.to_string().contains("invalid duration"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(
+            parse_duration("12h").unwrap(),
+            Duration::from_secs(12 * 3600)
+        );
+        assert_eq!(
+            parse_duration("30d").unwrap(),
+            Duration::from_secs(30 * 24 * 3600)
+        );
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3y").is_err());
+        assert!(parse_duration("-3d").is_err());
+        assert!(parse_duration("99999999999999999w").is_err());
     }
 }
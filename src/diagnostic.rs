@@ -0,0 +1,130 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// a message about a source file, optionally pointing at a position in it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub file: Option<PathBuf>,
+    pub location: Option<Location>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// 1-based line
+    pub line: usize,
+    /// 1-based column, counted in characters
+    pub column: usize,
+    /// the source line containing the position
+    pub source: String,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl ToString) -> Self {
+        Diagnostic {
+            message: message.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// point at the byte `offset` in `content`
+    pub fn at(message: impl ToString, content: &str, offset: usize) -> Self {
+        Diagnostic {
+            location: Some(Location::new(content, offset)),
+            ..Diagnostic::new(message)
+        }
+    }
+
+    pub fn with_file(mut self, file: &Path) -> Self {
+        self.file = Some(file.to_path_buf());
+        self
+    }
+}
+
+impl Location {
+    fn new(content: &str, offset: usize) -> Self {
+        let mut offset = offset.min(content.len());
+        while !content.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let before = &content[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line_end = content[offset..]
+            .find('\n')
+            .map_or(content.len(), |i| offset + i);
+
+        Location {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            source: content[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        let file = self.file.as_ref().map(|f| f.display().to_string());
+        match (&self.location, file) {
+            (Some(l), file) => {
+                let file = file.unwrap_or_else(|| "<config>".to_string());
+                let gutter = " ".repeat(l.line.to_string().len());
+                // keep tabs so the caret lines up with the source line
+                let indent: String = l
+                    .source
+                    .chars()
+                    .take(l.column - 1)
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect();
+                write!(f, "\n{}--> {}:{}:{}", gutter, file, l.line, l.column)?;
+                write!(f, "\n{} |", gutter)?;
+                write!(f, "\n{} | {}", l.line, l.source)?;
+                write!(f, "\n{} | {}^", gutter, indent)
+            }
+            (None, Some(file)) => write!(f, "\n --> {}", file),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location() {
+        let content = "{\n  \"image\": \"test\",\n  \"name\": }";
+        let l = Location::new(content, content.find('}').unwrap());
+        assert_eq!((l.line, l.column), (3, 11));
+        assert_eq!(l.source, "  \"name\": }");
+
+        let l = Location::new("{ \"näme\": }", 11);
+        assert_eq!((l.line, l.column), (1, 11));
+    }
+
+    #[test]
+    fn test_display() {
+        let content = "{\n  \"image\": \"test\",\n  \"name\": }";
+        let d = Diagnostic::at("Expected value", content, content.find('}').unwrap())
+            .with_file(Path::new("/src/aaa/.devcontainer.json"));
+        assert_eq!(
+            d.to_string(),
+            [
+                "Expected value",
+                " --> /src/aaa/.devcontainer.json:3:11",
+                "  |",
+                "3 |   \"name\": }",
+                "  |           ^",
+            ]
+            .join("\n")
+        );
+
+        let d = Diagnostic::new("image not specified").with_file(Path::new("/a.json"));
+        assert_eq!(d.to_string(), "image not specified\n --> /a.json");
+        assert_eq!(Diagnostic::new("empty").to_string(), "empty");
+    }
+}
@@ -0,0 +1,378 @@
+use super::{
+    client::{docker_cli, dry_run},
+    state::{process_socket, socket_mount, CONTAINER_SOCKET_DIR},
+};
+use crate::config::Forwarding;
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    net::Shutdown,
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+use tracing::{debug, info};
+
+pub const SSH_AUTH_SOCK: &str = "SSH_AUTH_SOCK";
+/// where the agent socket appears in the container when mounting the socket of docker desktop
+const CONTAINER_SOCKET: &str = "/tmp/devspace/ssh-agent.sock";
+/// directory of the relay sockets in the container
+const RELAY_DIR: &str = "/tmp/devspace";
+/// name of the proxy and relay sockets, made unique per process by `process_socket`
+const AGENT_SOCKET: &str = "ssh-agent";
+/// socket docker desktop provides in its vm for the agent of the macOS host, whose own
+/// sockets can not be mounted
+const DOCKER_DESKTOP_SOCKET: &str = "/run/host-services/ssh-auth.sock";
+/// upper bound of agent messages, as in OpenSSH
+const MAX_MESSAGE_LEN: usize = 256 * 1024;
+
+/// ssh agent of the host forwarded to the container
+///
+/// Mounting on linux shares the socket dir of the project rather than the agent socket, whose
+/// path changes with every login. Each devspace process proxies its current agent on a socket
+/// of its own there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SshAgent {
+    forwarding: Forwarding,
+    /// agent socket on the host, or in the docker vm
+    socket: PathBuf,
+    /// the agent is reached through the proxy socket in the socket dir
+    proxied: bool,
+}
+
+impl SshAgent {
+    /// the agent named by SSH_AUTH_SOCK, None if forwarding is off or no agent runs
+    pub fn from_host(forwarding: Forwarding) -> Option<Self> {
+        #[cfg(test)]
+        if super::fake::installed().is_some() {
+            return None;
+        }
+        let socket = std::env::var_os(SSH_AUTH_SOCK).map(PathBuf::from);
+        Self::new(forwarding, socket, std::env::consts::OS)
+    }
+
+    pub fn new(forwarding: Forwarding, socket: Option<PathBuf>, os: &str) -> Option<Self> {
+        let socket = socket.filter(|s| !s.as_os_str().is_empty())?;
+        let (socket, proxied) = match forwarding {
+            Forwarding::Off => return None,
+            Forwarding::Mount if os == "macos" => (PathBuf::from(DOCKER_DESKTOP_SOCKET), false),
+            Forwarding::Mount => (socket, true),
+            Forwarding::Relay => (socket, false),
+        };
+        Some(SshAgent {
+            forwarding,
+            socket,
+            proxied,
+        })
+    }
+
+    /// bind mount in `--mount` form of the socket dir `socket_dir` when proxying, else of the
+    /// agent socket, None when relaying
+    pub fn mount(&self, socket_dir: &Path) -> Option<String> {
+        if self.proxied {
+            return Some(socket_mount(socket_dir));
+        }
+        (self.forwarding == Forwarding::Mount).then(|| {
+            format!(
+                "type=bind,source={},target={}",
+                self.socket.display(),
+                CONTAINER_SOCKET
+            )
+        })
+    }
+
+    /// where the container needs a mount for the agent to be reachable, None when relaying
+    pub fn target(&self) -> Option<&str> {
+        match self.forwarding {
+            Forwarding::Mount if self.proxied => Some(CONTAINER_SOCKET_DIR),
+            Forwarding::Mount => Some(CONTAINER_SOCKET),
+            _ => None,
+        }
+    }
+
+    /// SSH_AUTH_SOCK of the processes this process runs in the container as `KEY=value`
+    pub fn env(&self) -> String {
+        let socket = match self.forwarding {
+            _ if self.proxied => {
+                format!("{}/{}", CONTAINER_SOCKET_DIR, process_socket(AGENT_SOCKET))
+            }
+            Forwarding::Relay => relay_socket(),
+            _ => CONTAINER_SOCKET.to_string(),
+        };
+        format!("{}={}", SSH_AUTH_SOCK, socket)
+    }
+
+    /// relay the agent into `container` until the returned relay is dropped, None unless
+    /// forwarding by relay
+    pub fn relay(&self, container: &str) -> Result<Option<Relay>> {
+        if self.forwarding != Forwarding::Relay || dry_run() {
+            return Ok(None);
+        }
+        Relay::start(&docker_cli()?, container, &self.socket).map(Some)
+    }
+
+    /// pass the connections to the proxy socket in `socket_dir` to the agent until the
+    /// returned proxy is dropped, None unless proxying
+    pub fn proxy(&self, socket_dir: &Path) -> Result<Option<Proxy>> {
+        if !self.proxied || dry_run() {
+            return Ok(None);
+        }
+        Proxy::start(&socket_dir.join(process_socket(AGENT_SOCKET)), &self.socket).map(Some)
+    }
+}
+
+/// listener in the socket dir connecting each of its connections to the agent of the host
+pub struct Proxy {
+    socket: PathBuf,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Proxy {
+    fn start(socket: &Path, agent: &Path) -> Result<Self> {
+        let _ = fs::remove_file(socket);
+        let listener = UnixListener::bind(socket)
+            .with_context(|| format!("failed to listen on {:?}", socket))?;
+        debug!("proxying the ssh agent {:?}", agent);
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let (s, agent) = (stopped.clone(), agent.to_path_buf());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if s.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Ok(stream) = stream else { break };
+                let agent = agent.clone();
+                thread::spawn(move || {
+                    if let Err(e) = connect(stream, &agent) {
+                        debug!("ssh agent connection failed: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(Proxy {
+            socket: socket.to_path_buf(),
+            stopped,
+        })
+    }
+}
+
+impl Drop for Proxy {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        // wake the listener so it sees it is stopped
+        let _ = UnixStream::connect(&self.socket);
+        let _ = fs::remove_file(&self.socket);
+    }
+}
+
+/// copy between `client` and a new connection to `agent` until either side closes
+fn connect(client: UnixStream, agent: &Path) -> io::Result<()> {
+    let upstream = UnixStream::connect(agent)?;
+    let (mut requests, mut answers) = (client.try_clone()?, upstream.try_clone()?);
+    let forward = thread::spawn(move || {
+        let _ = io::copy(&mut requests, &mut answers);
+        let _ = answers.shutdown(Shutdown::Write);
+    });
+    let (mut upstream, mut client) = (upstream, client);
+    io::copy(&mut upstream, &mut client)?;
+    let _ = client.shutdown(Shutdown::Write);
+    let _ = forward.join();
+    Ok(())
+}
+
+/// `docker exec` of socat listening on the relay socket of this process in the container,
+/// piping each connection through its stdio, where the messages are passed to the host agent
+///
+/// Connections are served one after another, which is what ssh and git need.
+pub struct Relay {
+    child: Child,
+}
+
+impl Relay {
+    fn start(docker: &Path, container: &str, agent: &Path) -> Result<Self> {
+        let socket = relay_socket();
+        let script = format!(
+            "command -v socat >/dev/null || {{ echo 'socat is needed to relay the ssh agent' >&2; exit 1; }}
+mkdir -p {RELAY_DIR} && while socat UNIX-LISTEN:{socket},unlink-early,mode=600 STDIO; do :; done"
+        );
+        let mut child = Command::new(docker)
+            .args(["exec", "-i", container, "sh", "-c", &script])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| format!("failed to run {:?}", docker))?;
+        info!("relaying the ssh agent");
+
+        let (requests, answers) = (child.stdout.take(), child.stdin.take());
+        let agent = agent.to_path_buf();
+        if let (Some(requests), Some(answers)) = (requests, answers) {
+            thread::spawn(move || {
+                if let Err(e) = pump(requests, answers, &agent) {
+                    debug!("ssh agent relay stopped: {}", e);
+                }
+            });
+        }
+        Ok(Relay { child })
+    }
+}
+
+impl Drop for Relay {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// socket the relay of this process listens on in the container
+fn relay_socket() -> String {
+    format!("{}/{}", RELAY_DIR, process_socket(AGENT_SOCKET))
+}
+
+/// pass the agent requests read from `requests` to the agent listening on `agent` and write
+/// its answers to `answers`, connecting on the first request
+fn pump(mut requests: impl Read, mut answers: impl Write, agent: &Path) -> io::Result<()> {
+    let mut connection: Option<UnixStream> = None;
+    while let Some(request) = read_message(&mut requests)? {
+        let stream = match &mut connection {
+            Some(stream) => stream,
+            None => connection.insert(UnixStream::connect(agent)?),
+        };
+        stream.write_all(&request)?;
+        let answer = read_message(stream)?.ok_or(io::ErrorKind::UnexpectedEof)?;
+        answers.write_all(&answer)?;
+        answers.flush()?;
+    }
+    Ok(())
+}
+
+/// a length prefixed agent message including its length, None at the end of the stream
+fn read_message(r: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0; 4];
+    match r.read_exact(&mut len) {
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+    let n = u32::from_be_bytes(len) as usize;
+    if n > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("agent message of {} bytes", n),
+        ));
+    }
+    let mut message = len.to_vec();
+    message.resize(4 + n, 0);
+    r.read_exact(&mut message[4..])?;
+    Ok(Some(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_new() {
+        let socket = || Some(PathBuf::from("/tmp/agent.sock"));
+        let dir = Path::new("/run/devspace/aaa");
+        let agent = SshAgent::new(Forwarding::Mount, socket(), "linux").unwrap();
+        assert_eq!(
+            agent.mount(dir).unwrap(),
+            "type=bind,source=/run/devspace/aaa,target=/tmp/devspace/host"
+        );
+        assert_eq!(agent.target(), Some("/tmp/devspace/host"));
+        assert_eq!(
+            agent.env(),
+            format!(
+                "SSH_AUTH_SOCK=/tmp/devspace/host/ssh-agent-{}.sock",
+                std::process::id()
+            )
+        );
+
+        let agent = SshAgent::new(Forwarding::Mount, socket(), "macos").unwrap();
+        assert_eq!(
+            agent.mount(dir).unwrap(),
+            "type=bind,source=/run/host-services/ssh-auth.sock,target=/tmp/devspace/ssh-agent.sock"
+        );
+        assert_eq!(agent.target(), Some("/tmp/devspace/ssh-agent.sock"));
+        assert_eq!(agent.env(), "SSH_AUTH_SOCK=/tmp/devspace/ssh-agent.sock");
+
+        let agent = SshAgent::new(Forwarding::Relay, socket(), "macos").unwrap();
+        assert_eq!(agent.mount(dir), None);
+        assert_eq!(agent.target(), None);
+        assert_eq!(agent.socket, Path::new("/tmp/agent.sock"));
+        assert_eq!(
+            agent.env(),
+            format!(
+                "SSH_AUTH_SOCK=/tmp/devspace/ssh-agent-{}.sock",
+                std::process::id()
+            )
+        );
+
+        assert_eq!(SshAgent::new(Forwarding::Off, socket(), "linux"), None);
+        assert_eq!(SshAgent::new(Forwarding::Mount, None, "linux"), None);
+        assert_eq!(
+            SshAgent::new(Forwarding::Mount, Some(PathBuf::new()), "linux"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pump() {
+        let dir = TempDir::new().unwrap();
+        let socket = dir.path().join("agent.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+        // answers every request with its type byte plus one
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            while let Some(request) = read_message(&mut stream).unwrap() {
+                stream.write_all(&[0, 0, 0, 1, request[4] + 1]).unwrap();
+            }
+        });
+
+        let requests = [0, 0, 0, 1, 11, 0, 0, 0, 3, 13, 0, 0];
+        let mut answers = vec![];
+        pump(&requests[..], &mut answers, &socket).unwrap();
+        assert_eq!(answers, [0, 0, 0, 1, 12, 0, 0, 0, 1, 14]);
+
+        let err = pump(&[0, 0xff, 0, 0][..], &mut answers, &socket).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_proxy() {
+        let dir = TempDir::new().unwrap();
+        let agent = dir.path().join("agent.sock");
+        let listener = UnixListener::bind(&agent).unwrap();
+        // answers every request with its type byte plus one
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                while let Some(request) = read_message(&mut stream).unwrap() {
+                    stream.write_all(&[0, 0, 0, 1, request[4] + 1]).unwrap();
+                }
+            }
+        });
+
+        let socket = dir.path().join(process_socket(AGENT_SOCKET));
+        let proxy = Proxy::start(&socket, &agent).unwrap();
+        for request in [11, 13] {
+            let mut stream = UnixStream::connect(&socket).unwrap();
+            stream.write_all(&[0, 0, 0, 1, request]).unwrap();
+            assert_eq!(
+                read_message(&mut stream).unwrap().unwrap(),
+                [0, 0, 0, 1, request + 1]
+            );
+        }
+
+        drop(proxy);
+        assert!(!socket.exists());
+    }
+}
@@ -0,0 +1,282 @@
+use anyhow::{anyhow, Context, Result};
+use std::{
+    fs, io,
+    path::{Component, Path},
+};
+
+/// tar `src` under the name `name`, directories recursively, modes are kept and symlinks are
+/// stored as links
+pub fn pack(src: &Path, name: &str) -> Result<Vec<u8>> {
+    let metadata = fs::symlink_metadata(src).with_context(|| format!("can not read {:?}", src))?;
+    let mut builder = tar::Builder::new(Vec::new());
+    builder.follow_symlinks(false);
+    if metadata.is_dir() {
+        builder.append_dir_all(name, src)?;
+    } else {
+        builder.append_path_with_name(src, name)?;
+    }
+    builder
+        .into_inner()
+        .with_context(|| format!("can not archive {:?}", src))
+}
+
+/// tar `data` as a file named `name` with mode 0644
+pub fn pack_data(data: &[u8], name: &str) -> Result<Vec<u8>> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    let mut builder = tar::Builder::new(Vec::new());
+    builder.append_data(&mut header, name, data)?;
+    Ok(builder.into_inner()?)
+}
+
+/// unpack an archive as returned by the docker archive endpoint, the top level entry is
+/// written to `dst` and everything below it into `dst`, never following symlinks or hard links
+/// of the archive out of `dst`
+pub fn unpack(archive: &[u8], dst: &Path) -> Result<()> {
+    let mut archive = tar::Archive::new(archive);
+    archive.set_preserve_permissions(true);
+    archive.set_preserve_mtime(true);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let rest = below_top(&path)?;
+        // joining an empty path would add a trailing slash
+        let target = if rest.as_os_str().is_empty() {
+            dst.to_path_buf()
+        } else {
+            dst.join(rest)
+        };
+        create_parents(dst, rest)?;
+        // replace a link of the archive instead of writing to where it points
+        if fs::symlink_metadata(&target).is_ok_and(|m| m.is_symlink()) {
+            fs::remove_file(&target).with_context(|| format!("can not replace {:?}", target))?;
+        }
+
+        // tar links to the name as given, relative to the working directory, link to the
+        // entry unpacked before instead
+        if entry.header().entry_type().is_hard_link() {
+            let name = entry
+                .link_name()?
+                .ok_or_else(|| anyhow!("hard link {:?} in archive has no target", path))?;
+            let source = dst.join(below_top(&name)?);
+            if !unpacked_below(dst, &source) {
+                return Err(anyhow!(
+                    "invalid hard link {:?} to {:?} in archive",
+                    path,
+                    name
+                ));
+            }
+            fs::hard_link(&source, &target)
+                .with_context(|| format!("can not write {:?}", target))?;
+            continue;
+        }
+        entry
+            .unpack(&target)
+            .with_context(|| format!("can not write {:?}", target))?;
+    }
+    Ok(())
+}
+
+/// the part of an archive path below its top level entry, which is the only one allowed to be
+/// anything but a plain name
+fn below_top(path: &Path) -> Result<&Path> {
+    let mut components = path.components();
+    components.next();
+    let rest = components.as_path();
+    if !rest.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(anyhow!("invalid path {:?} in archive", path));
+    }
+    Ok(rest)
+}
+
+/// whether `path` is a file below the directory `dst`, without going through symlinks
+fn unpacked_below(dst: &Path, path: &Path) -> bool {
+    let Ok(rest) = path.strip_prefix(dst) else {
+        return false;
+    };
+    let mut dir = dst.to_path_buf();
+    for component in rest.parent().into_iter().flat_map(|p| p.components()) {
+        dir.push(component);
+        if !fs::symlink_metadata(&dir).is_ok_and(|m| m.is_dir()) {
+            return false;
+        }
+    }
+    !rest.as_os_str().is_empty() && fs::symlink_metadata(path).is_ok_and(|m| m.is_file())
+}
+
+/// create the directories `dst` and the parents of `rest` below it, refusing to go through
+/// anything but directories, like symlinks unpacked before
+fn create_parents(dst: &Path, rest: &Path) -> Result<()> {
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("can not create directory {:?}", parent))?;
+    }
+    let Some(parents) = rest.parent() else {
+        return Ok(());
+    };
+    let mut dir = dst.to_path_buf();
+    let mut components = parents.components();
+    loop {
+        match fs::symlink_metadata(&dir) {
+            Ok(m) if m.is_dir() => {}
+            Ok(_) => {
+                return Err(anyhow!(
+                    "can not unpack {:?}, {:?} is not a directory",
+                    rest,
+                    dir
+                ))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => fs::create_dir(&dir)
+                .with_context(|| format!("can not create directory {:?}", dir))?,
+            Err(e) => return Err(e).with_context(|| format!("can not read {:?}", dir)),
+        }
+        match components.next() {
+            Some(component) => dir.push(component),
+            None => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pack_unpack() {
+        let tmpdir = TempDir::new().unwrap();
+        let src = tmpdir.path().join("src");
+        fs::create_dir_all(src.join("bin")).unwrap();
+        fs::write(src.join("README"), "hello").unwrap();
+        fs::write(src.join("bin").join("run"), "#!/bin/sh").unwrap();
+        fs::set_permissions(
+            src.join("bin").join("run"),
+            fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+
+        let archive = pack(&src, "aaa").unwrap();
+        let names: Vec<String> = tar::Archive::new(archive.as_slice())
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().display().to_string())
+            .collect();
+        assert!(names.contains(&"aaa/bin/run".to_string()), "{:?}", names);
+
+        let dst = tmpdir.path().join("dst");
+        unpack(&archive, &dst).unwrap();
+        assert_eq!(fs::read_to_string(dst.join("README")).unwrap(), "hello");
+        let mode = fs::metadata(dst.join("bin").join("run"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
+
+    #[test]
+    fn test_pack_file() {
+        let tmpdir = TempDir::new().unwrap();
+        let src = tmpdir.path().join("a.txt");
+        fs::write(&src, "a").unwrap();
+
+        let archive = pack(&src, "b.txt").unwrap();
+        let dst = tmpdir.path().join("out").join("c.txt");
+        unpack(&archive, &dst).unwrap();
+        assert_eq!(fs::read_to_string(dst).unwrap(), "a");
+    }
+
+    #[test]
+    fn test_pack_data() {
+        let archive = pack_data(b"a", "b.txt").unwrap();
+        let tmpdir = TempDir::new().unwrap();
+        let dst = tmpdir.path().join("b.txt");
+        unpack(&archive, &dst).unwrap();
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "a");
+        let mode = fs::metadata(dst).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o644);
+    }
+
+    #[test]
+    fn test_unpack_rejects_parent_dirs() {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(1);
+        header.set_mode(0o644);
+        // set_path refuses `..`, write the raw name like a malicious archive would
+        header.as_old_mut().name[..11].copy_from_slice(b"aaa/../evil");
+        header.set_cksum();
+        let mut builder = tar::Builder::new(Vec::new());
+        builder.append(&header, &b"x"[..]).unwrap();
+        let archive = builder.into_inner().unwrap();
+
+        let tmpdir = TempDir::new().unwrap();
+        let err = unpack(&archive, &tmpdir.path().join("dst")).unwrap_err();
+        assert!(err.to_string().contains("invalid path"), "{}", err);
+    }
+
+    #[test]
+    fn test_unpack_rejects_symlink_escape() {
+        let tmpdir = TempDir::new().unwrap();
+        let outside = tmpdir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_mode(0o755);
+        builder.append_data(&mut header, "d", io::empty()).unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "d/link", &outside)
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(3);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "d/link/authorized_keys", &b"key"[..])
+            .unwrap();
+        let archive = builder.into_inner().unwrap();
+
+        let dst = tmpdir.path().join("dst");
+        let err = unpack(&archive, &dst).unwrap_err();
+        assert!(err.to_string().contains("is not a directory"), "{}", err);
+        assert!(!outside.join("authorized_keys").exists());
+        assert!(fs::symlink_metadata(dst.join("link")).unwrap().is_symlink());
+    }
+
+    #[test]
+    fn test_unpack_hard_links() {
+        let tmpdir = TempDir::new().unwrap();
+        let outside = tmpdir.path().join("outside");
+        fs::write(&outside, "secret").unwrap();
+
+        let archive = |link: &Path| {
+            let mut builder = tar::Builder::new(Vec::new());
+            let mut header = tar::Header::new_gnu();
+            header.set_size(1);
+            header.set_mode(0o644);
+            builder
+                .append_data(&mut header, "aaa/a", &b"a"[..])
+                .unwrap();
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Link);
+            header.set_size(0);
+            builder.append_link(&mut header, "aaa/b", link).unwrap();
+            builder.into_inner().unwrap()
+        };
+
+        let dst = tmpdir.path().join("dst");
+        unpack(&archive(Path::new("aaa/a")), &dst).unwrap();
+        assert_eq!(fs::read_to_string(dst.join("b")).unwrap(), "a");
+
+        let dst = tmpdir.path().join("escape");
+        let err = unpack(&archive(&outside), &dst).unwrap_err();
+        assert!(err.to_string().contains("invalid"), "{}", err);
+        assert!(!dst.join("b").exists());
+    }
+}
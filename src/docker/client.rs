@@ -1,40 +1,151 @@
-use crate::docker::process;
+use super::{dry_run::DryRunClient, process};
+use crate::{config, error::DevspaceError};
 use anyhow::{Context, Result};
 use bollard::{
-    container::{ListContainersOptions, StartContainerOptions, StopContainerOptions},
-    image::ListImagesOptions,
+    container::{
+        DownloadFromContainerOptions, ListContainersOptions, LogOutput, LogsOptions,
+        RemoveContainerOptions, StartContainerOptions, StopContainerOptions,
+        UploadToContainerOptions,
+    },
+    errors::Error as BollardError,
+    image::{ListImagesOptions, RemoveImageOptions},
     models::{ContainerSummary, ImageSummary},
     Docker,
 };
-use std::{collections::HashMap, path::PathBuf};
+use futures_util::StreamExt;
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
 use tokio::runtime::Builder;
+use tracing::{debug, info};
 
-const PROJECT_KEY: &str = "ds_project";
+pub const PROJECT_KEY: &str = "ds_project";
+pub const CONFIG_HASH_KEY: &str = "ds_config_hash";
+pub const ROOT_KEY: &str = "ds_root";
+pub const NAME_KEY: &str = "ds_name";
+/// environment variable naming the docker executable
+pub const DOCKER_ENV: &str = "DEVSPACE_DOCKER";
 
 pub trait DockerClient {
-    fn list_containers(&self, project_name: &str) -> Result<Vec<ContainerSummary>>;
-    fn list_images(&self, project_name: &str) -> Result<Vec<ImageSummary>>;
-    fn build_image(&self, project_name: &str, dockerfile: &str) -> Result<()>;
+    fn list_containers(&self, project_id: &str) -> Result<Vec<ContainerSummary>>;
+    fn list_images(&self, project_id: &str) -> Result<Vec<ImageSummary>>;
+    /// containers of all projects, with `size` including their writable layer size
+    fn list_all_containers(&self, size: bool) -> Result<Vec<ContainerSummary>>;
+    /// images of all projects, including untagged ones
+    fn list_all_images(&self) -> Result<Vec<ImageSummary>>;
+    fn build_image(
+        &self,
+        tag: &str,
+        dockerfile: &str,
+        context: &Path,
+        labels: HashMap<&str, &str>,
+    ) -> Result<()>;
     fn start_container(&self, name: &str) -> Result<()>;
     fn stop_container(&self, name: &str) -> Result<()>;
-    fn run(&self, name: &str, image_name: &str, deattach: bool, args: Vec<&str>) -> Result<()>;
-    fn exec(&self, contain_name: &str, cmd: &[&str]) -> Result<()>;
+    fn remove_container(&self, id: &str) -> Result<()>;
+    fn remove_image(&self, id: &str) -> Result<()>;
+    fn run(&self, options: &RunOptions) -> Result<()>;
+    fn exec(&self, options: &ExecOptions) -> Result<()>;
+    /// write the container output to stdout and stderr
+    fn logs(&self, name: &str, options: &LogOptions) -> Result<()>;
+    /// extract a tar archive into the directory `dir` of the container
+    fn upload(&self, name: &str, dir: &str, archive: Vec<u8>) -> Result<()>;
+    /// tar archive of `path` in the container
+    fn download(&self, name: &str, path: &str) -> Result<Vec<u8>>;
+    /// whether `path` is an existing directory in the container
+    fn is_dir(&self, name: &str, path: &str) -> Result<bool>;
+    /// resources of the docker host
+    fn host_info(&self) -> Result<HostInfo>;
+}
+
+/// resources of the docker host, zero where unknown
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostInfo {
+    pub cpus: u64,
+    /// bytes
+    pub memory: u64,
+    /// directory docker keeps images and containers in, on the docker host
+    pub root_dir: Option<String>,
+}
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// make the clients returned by `new_client` print their mutations instead of running them
+pub fn set_dry_run(dry_run: bool) {
+    DRY_RUN.store(dry_run, Ordering::Relaxed);
+}
+
+pub fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
 }
 
 pub fn new_client() -> Result<Box<dyn DockerClient>> {
-    let client = Docker::connect_with_local_defaults()?;
+    if dry_run() {
+        // reads still go to the daemon, so the plan starts from the actual state
+        let inner = connect()
+            .inspect_err(|e| info!("{:#}, planning as if nothing exists", e))
+            .ok();
+        return Ok(Box::new(DryRunClient::new(inner)));
+    }
+    connect()
+}
+
+fn connect() -> Result<Box<dyn DockerClient>> {
+    #[cfg(test)]
+    if let Some(fake) = super::fake::installed() {
+        return Ok(Box::new(DockerClientImpl::with_socket(
+            &fake.socket,
+            fake.cli,
+        )?));
+    }
+    let client = Docker::connect_with_local_defaults().map_err(unreachable)?;
+    ping(&client)?;
     let cli = DockerCli::new()?;
     Ok(Box::new(DockerClientImpl { client, cli }))
 }
 
+fn unreachable(e: BollardError) -> DevspaceError {
+    DevspaceError::DockerUnreachable {
+        message: e.to_string(),
+    }
+}
+
+fn ping(client: &Docker) -> Result<()> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(client.ping()).map_err(unreachable)?;
+    Ok(())
+}
+
 pub struct DockerClientImpl {
     client: Docker,
     cli: DockerCli,
 }
 
+impl DockerClientImpl {
+    /// client of the daemon listening on `socket`, running `cli` for builds, runs and execs
+    #[cfg(test)]
+    pub fn with_socket(socket: &Path, cli: PathBuf) -> Result<Self> {
+        let client = Docker::connect_with_unix(
+            &socket.display().to_string(),
+            120,
+            bollard::API_DEFAULT_VERSION,
+        )
+        .map_err(unreachable)?;
+        ping(&client)?;
+        Ok(DockerClientImpl {
+            client,
+            cli: DockerCli { command: cli },
+        })
+    }
+}
+
 impl DockerClient for DockerClientImpl {
-    fn list_containers(&self, project_name: &str) -> Result<Vec<ContainerSummary>> {
-        let label = format!("{}={}", PROJECT_KEY, &project_name);
+    fn list_containers(&self, project_id: &str) -> Result<Vec<ContainerSummary>> {
+        let label = format!("{}={}", PROJECT_KEY, &project_id);
         let options = ListContainersOptions {
             all: true,
             filters: HashMap::from([("label", vec![label.as_ref()])]),
@@ -46,8 +157,8 @@ impl DockerClient for DockerClientImpl {
             .context("can not list containers")
     }
 
-    fn list_images(&self, project_name: &str) -> Result<Vec<ImageSummary>> {
-        let label = format!("{}={}", PROJECT_KEY, project_name);
+    fn list_images(&self, project_id: &str) -> Result<Vec<ImageSummary>> {
+        let label = format!("{}={}", PROJECT_KEY, project_id);
 
         let options = ListImagesOptions {
             all: true,
@@ -60,18 +171,48 @@ impl DockerClient for DockerClientImpl {
             .context("can not list images")
     }
 
-    fn build_image(&self, project_name: &str, dockerfile: &str) -> Result<()> {
-        let options = BuildOptions {
-            tag: &format!("{}:latest", project_name),
-            path: PathBuf::from("."),
-            dockerfile,
-            labels: HashMap::from([(PROJECT_KEY, project_name)]),
+    fn list_all_containers(&self, size: bool) -> Result<Vec<ContainerSummary>> {
+        let options = ListContainersOptions {
+            all: true,
+            size,
+            filters: HashMap::from([("label", vec![PROJECT_KEY])]),
+            ..Default::default()
+        };
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        runtime
+            .block_on(self.client.list_containers(Some(options)))
+            .context("can not list containers")
+    }
+
+    fn list_all_images(&self) -> Result<Vec<ImageSummary>> {
+        let options = ListImagesOptions {
+            all: true,
+            filters: HashMap::from([("label", vec![PROJECT_KEY])]),
             ..Default::default()
         };
-        self.cli.build(&options)
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        runtime
+            .block_on(self.client.list_images(Some(options)))
+            .context("can not list images")
+    }
+
+    fn build_image(
+        &self,
+        tag: &str,
+        dockerfile: &str,
+        context: &Path,
+        labels: HashMap<&str, &str>,
+    ) -> Result<()> {
+        let options = BuildOptions::new(tag, dockerfile, context, labels);
+        self.cli
+            .build(&options)
+            .context(DevspaceError::ImageBuildFailed {
+                image: tag.to_string(),
+            })
     }
 
     fn start_container(&self, name: &str) -> Result<()> {
+        debug!("starting container {}", name);
         let runtime = Builder::new_current_thread().enable_all().build()?;
         let options: StartContainerOptions<String> = Default::default();
         runtime
@@ -80,6 +221,7 @@ impl DockerClient for DockerClientImpl {
     }
 
     fn stop_container(&self, name: &str) -> Result<()> {
+        debug!("stopping container {}", name);
         let runtime = Builder::new_current_thread().enable_all().build()?;
         let options: StopContainerOptions = Default::default();
         runtime
@@ -87,29 +229,142 @@ impl DockerClient for DockerClientImpl {
             .context("can not stop container")
     }
 
-    fn run(&self, name: &str, image_name: &str, deattach: bool, args: Vec<&str>) -> Result<()> {
-        let options = RunOptions {
-            name,
-            image: image_name,
-            deattach,
-            args,
+    fn remove_container(&self, id: &str) -> Result<()> {
+        debug!("removing container {}", id);
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        let options = RemoveContainerOptions {
+            force: true,
             ..Default::default()
         };
-        self.cli.run(&options)
+        runtime
+            .block_on(self.client.remove_container(id, Some(options)))
+            .context("can not remove container")
     }
 
-    fn exec(&self, contain_name: &str, cmd: &[&str]) -> Result<()> {
-        let options = ExecOptions {
-            container: contain_name,
-            args: Vec::from(cmd),
+    fn remove_image(&self, id: &str) -> Result<()> {
+        debug!("removing image {}", id);
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        let options: RemoveImageOptions = Default::default();
+        runtime
+            .block_on(self.client.remove_image(id, Some(options), None))
+            .map(|_| ())
+            .context("can not remove image")
+    }
+
+    fn run(&self, options: &RunOptions) -> Result<()> {
+        self.cli.run(options)
+    }
+
+    fn exec(&self, options: &ExecOptions) -> Result<()> {
+        self.cli.exec(options)
+    }
+
+    fn logs(&self, name: &str, options: &LogOptions) -> Result<()> {
+        let options = LogsOptions::<String> {
+            follow: options.follow,
+            stdout: true,
+            stderr: true,
+            since: options.since.unwrap_or_default(),
+            timestamps: options.timestamps,
+            tail: options
+                .tail
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| "all".to_string()),
             ..Default::default()
         };
-        self.cli.exec(&options)
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        runtime.block_on(async {
+            let mut stream = self.client.logs(name, Some(options));
+            while let Some(output) = stream.next().await {
+                match output.context("can not read container logs")? {
+                    LogOutput::StdErr { message } => std::io::stderr().write_all(&message)?,
+                    output => std::io::stdout().write_all(output.as_ref())?,
+                }
+            }
+            Ok(())
+        })
     }
+
+    fn upload(&self, name: &str, dir: &str, archive: Vec<u8>) -> Result<()> {
+        debug!("uploading {} bytes to {}:{}", archive.len(), name, dir);
+        let options = UploadToContainerOptions {
+            path: dir,
+            ..Default::default()
+        };
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        runtime
+            .block_on(
+                self.client
+                    .upload_to_container(name, Some(options), archive.into()),
+            )
+            .with_context(|| format!("can not copy to {}:{}", name, dir))
+    }
+
+    fn download(&self, name: &str, path: &str) -> Result<Vec<u8>> {
+        debug!("downloading {}:{}", name, path);
+        let options = DownloadFromContainerOptions { path };
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        runtime.block_on(async {
+            let mut stream = self.client.download_from_container(name, Some(options));
+            let mut archive = vec![];
+            while let Some(chunk) = stream.next().await {
+                let chunk =
+                    chunk.with_context(|| format!("can not copy from {}:{}", name, path))?;
+                archive.extend_from_slice(&chunk);
+            }
+            Ok(archive)
+        })
+    }
+
+    fn host_info(&self) -> Result<HostInfo> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        let info = runtime.block_on(self.client.info())?;
+        Ok(HostInfo {
+            cpus: info.ncpu.unwrap_or_default().max(0) as u64,
+            memory: info.mem_total.unwrap_or_default().max(0) as u64,
+            root_dir: info.docker_root_dir,
+        })
+    }
+
+    fn is_dir(&self, name: &str, path: &str) -> Result<bool> {
+        let options = DownloadFromContainerOptions { path };
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        runtime.block_on(async {
+            // the first tar header describes `path`, no need to download the rest
+            let mut stream = self.client.download_from_container(name, Some(options));
+            let mut header = vec![];
+            while header.len() < 512 {
+                match stream.next().await {
+                    Some(Ok(chunk)) => header.extend_from_slice(&chunk),
+                    Some(Err(BollardError::DockerResponseServerError {
+                        status_code: 404, ..
+                    }))
+                    | None => return Ok(false),
+                    Some(Err(e)) => {
+                        return Err(e).with_context(|| format!("can not stat {}:{}", name, path))
+                    }
+                }
+            }
+            Ok(tar::Header::from_byte_slice(&header[..512])
+                .entry_type()
+                .is_dir())
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LogOptions {
+    /// keep streaming new output
+    pub follow: bool,
+    /// unix timestamp of the oldest entry to show
+    pub since: Option<i64>,
+    /// number of lines to show from the end
+    pub tail: Option<usize>,
+    pub timestamps: bool,
 }
 
 #[derive(Debug)]
-struct BuildOptions<'a> {
+pub struct BuildOptions<'a> {
     cmd: &'static str,
     tag: &'a str,
     dockerfile: &'a str,
@@ -117,8 +372,23 @@ struct BuildOptions<'a> {
     path: PathBuf,
 }
 
-impl<'a> BuildOptions<'_> {
-    fn build(&'a self) -> Vec<String> {
+impl<'a> BuildOptions<'a> {
+    pub fn new(
+        tag: &'a str,
+        dockerfile: &'a str,
+        context: &Path,
+        labels: HashMap<&'a str, &'a str>,
+    ) -> Self {
+        BuildOptions {
+            tag,
+            path: context.to_path_buf(),
+            dockerfile,
+            labels,
+            ..Default::default()
+        }
+    }
+
+    pub fn build(&self) -> Vec<String> {
         let mut args = vec![
             self.cmd.to_string(),
             "-t".to_string(),
@@ -126,10 +396,7 @@ impl<'a> BuildOptions<'_> {
             "-f".to_string(),
             self.dockerfile.to_string(),
         ];
-        args.push("--label".to_string());
-        for (key, value) in &self.labels {
-            args.push(format!("{}={}", key, value));
-        }
+        args.extend(label_args(&self.labels));
         args.push(self.path.display().to_string());
         args
     }
@@ -147,30 +414,77 @@ impl Default for BuildOptions<'_> {
     }
 }
 
-#[derive(Debug)]
-struct RunOptions<'a> {
-    cmd: &'static str,
-    pub name: &'a str,
+#[derive(Debug, Default)]
+pub struct RunOptions<'a> {
+    /// None lets docker pick a name
+    pub name: Option<&'a str>,
+    /// remove the container when it exits
+    pub remove: bool,
     pub deattach: bool,
+    /// keep stdin open
+    pub interactive: bool,
+    pub tty: bool,
     pub image: &'a str,
-    pub volume: HashMap<&'a str, &'a str>,
+    pub labels: HashMap<&'a str, &'a str>,
+    /// mounts in `--mount` form
+    pub mounts: Vec<String>,
+    /// `KEY=value` pairs
+    pub env: Vec<String>,
+    pub workdir: Option<&'a str>,
+    pub cpus: Option<f64>,
+    /// memory limit in bytes
+    pub memory: Option<u64>,
+    pub pids_limit: Option<i64>,
     pub args: Vec<&'a str>,
 }
 
 impl RunOptions<'_> {
-    fn build(&self) -> Vec<String> {
-        let mut args = vec![
-            self.cmd.to_string(),
-            "--name".to_string(),
-            self.name.to_string(),
-        ];
+    pub fn build(&self) -> Vec<String> {
+        let mut args = vec!["run".to_string()];
+        if let Some(name) = self.name {
+            args.push("--name".to_string());
+            args.push(name.to_string());
+        }
+        if self.remove {
+            args.push("--rm".to_string())
+        }
         if self.deattach {
             args.push("-d".to_string())
         }
+        if self.interactive {
+            args.push("-i".to_string())
+        }
+        if self.tty {
+            args.push("-t".to_string())
+        }
+        args.extend(label_args(&self.labels));
+
+        for mount in &self.mounts {
+            args.push("--mount".to_string());
+            args.push(mount.to_string());
+        }
+
+        for env in &self.env {
+            args.push("-e".to_string());
+            args.push(env.to_string());
+        }
 
-        for (key, value) in &self.volume {
-            args.push("-v".to_string());
-            args.push(format!("{}:{}", key, value));
+        if let Some(workdir) = self.workdir {
+            args.push("-w".to_string());
+            args.push(workdir.to_string());
+        }
+
+        if let Some(cpus) = self.cpus {
+            args.push("--cpus".to_string());
+            args.push(cpus.to_string());
+        }
+        if let Some(memory) = self.memory {
+            args.push("--memory".to_string());
+            args.push(memory.to_string());
+        }
+        if let Some(pids_limit) = self.pids_limit {
+            args.push("--pids-limit".to_string());
+            args.push(pids_limit.to_string());
         }
 
         args.push(self.image.to_string());
@@ -179,55 +493,92 @@ impl RunOptions<'_> {
     }
 }
 
-impl Default for RunOptions<'_> {
-    fn default() -> Self {
-        RunOptions {
-            cmd: "run",
-            name: Default::default(),
-            deattach: false,
-            image: Default::default(),
-            volume: Default::default(),
-            args: Default::default(),
-        }
-    }
+/// `--label` arguments sorted by key, so the same options always give the same command line
+fn label_args(labels: &HashMap<&str, &str>) -> Vec<String> {
+    let mut labels: Vec<_> = labels.iter().collect();
+    labels.sort();
+    labels
+        .into_iter()
+        .flat_map(|(key, value)| ["--label".to_string(), format!("{}={}", key, value)])
+        .collect()
 }
 
+#[derive(Debug, Default)]
 pub struct ExecOptions<'a> {
-    cmd: &'static str,
     pub container: &'a str,
+    /// `KEY=value` pairs
+    pub env: Vec<String>,
+    pub workdir: Option<&'a str>,
     pub args: Vec<&'a str>,
+    /// file the output is appended to, besides showing it
+    pub log: Option<&'a Path>,
 }
 
 impl ExecOptions<'_> {
-    fn build(&self) -> Vec<String> {
-        let mut args = vec![
-            self.cmd.to_owned(),
-            "-it".to_owned(),
-            self.container.to_owned(),
-        ];
+    pub fn build(&self) -> Vec<String> {
+        let mut args = vec!["exec".to_owned(), "-it".to_owned()];
+        for env in &self.env {
+            args.push("-e".to_string());
+            args.push(env.to_string());
+        }
+        if let Some(workdir) = self.workdir {
+            args.push("-w".to_string());
+            args.push(workdir.to_string());
+        }
+        args.push(self.container.to_owned());
         args.extend(self.args.iter().map(|s| s.to_string()));
         args
     }
 }
 
-impl Default for ExecOptions<'_> {
-    fn default() -> Self {
-        ExecOptions {
-            cmd: "exec",
-            container: Default::default(),
-            args: Default::default(),
-        }
+/// where the docker executable to run is configured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DockerSource {
+    /// DEVSPACE_DOCKER
+    Env,
+    /// the docker setting of the global config
+    Global,
+    /// `docker` in PATH
+    Default,
+}
+
+/// the docker executable named by the environment, else by the global config, else `docker`
+fn docker_command(env: Option<OsString>, configured: Option<String>) -> (OsString, DockerSource) {
+    match (env.filter(|e| !e.is_empty()), configured) {
+        (Some(env), _) => (env, DockerSource::Env),
+        (None, Some(configured)) => (configured.into(), DockerSource::Global),
+        (None, None) => ("docker".into(), DockerSource::Default),
     }
 }
 
+/// path of the docker executable picked by the environment and the `configured` one of the
+/// global config, and where it was picked from
+pub fn find_docker_cli(configured: Option<String>) -> Result<(PathBuf, DockerSource)> {
+    let (name, source) = docker_command(std::env::var_os(DOCKER_ENV), configured);
+    let path = which::which(&name)
+        .with_context(|| format!("can not find docker executable {:?}", name))?;
+    Ok((path, source))
+}
+
+/// path of the docker executable picked by the environment and the global config
+pub fn docker_cli() -> Result<PathBuf> {
+    #[cfg(test)]
+    if let Some(fake) = super::fake::installed() {
+        return Ok(fake.cli);
+    }
+    let (path, _) = find_docker_cli(config::Config::new()?.docker)?;
+    Ok(path)
+}
+
 struct DockerCli {
     command: PathBuf,
 }
 
 impl DockerCli {
     fn new() -> Result<Self> {
-        let command = which::which("docker").context("can not find docker executable")?;
-        Ok(DockerCli { command })
+        Ok(DockerCli {
+            command: docker_cli()?,
+        })
     }
 
     fn build(&self, options: &BuildOptions) -> Result<()> {
@@ -240,7 +591,10 @@ impl DockerCli {
     }
 
     fn exec(&self, options: &ExecOptions) -> Result<()> {
-        process::pipe_cmd(&self.command, options.build())
+        match options.log {
+            Some(log) => process::tee_cmd(&self.command, options.build(), log),
+            None => process::pipe_cmd(&self.command, options.build()),
+        }
     }
 }
 
@@ -256,9 +610,13 @@ pub mod tests {
             client: Docker::connect_with_local_defaults().unwrap(),
             cli: DockerCli::new().unwrap(),
         };
-        client
-            .run("dev_space_test", "alpine", false, vec!["ls", "-l"])
-            .unwrap();
+        let options = RunOptions {
+            name: Some("dev_space_test"),
+            image: "alpine",
+            args: vec!["ls", "-l"],
+            ..Default::default()
+        };
+        client.run(&options).unwrap();
     }
 
     #[ignore]
@@ -273,17 +631,139 @@ pub mod tests {
         assert_eq!(containers[0].names.as_ref().unwrap()[0], "/dev_space_test");
     }
 
+    #[test]
+    fn test_build_options_labels() {
+        let options = BuildOptions {
+            tag: "aaa:latest",
+            labels: HashMap::from([(PROJECT_KEY, "aaa"), (ROOT_KEY, "/src/aaa")]),
+            ..Default::default()
+        };
+        let args = options.build();
+        assert_eq!(
+            args[5..],
+            [
+                "--label",
+                "ds_project=aaa",
+                "--label",
+                "ds_root=/src/aaa",
+                "."
+            ]
+        );
+    }
+
+    #[test]
+    fn test_docker_command() {
+        let podman = || Some("podman".to_string());
+        assert_eq!(
+            docker_command(None, None),
+            ("docker".into(), DockerSource::Default)
+        );
+        assert_eq!(
+            docker_command(None, podman()),
+            ("podman".into(), DockerSource::Global)
+        );
+        assert_eq!(
+            docker_command(Some("/opt/docker".into()), podman()),
+            ("/opt/docker".into(), DockerSource::Env)
+        );
+        assert_eq!(
+            docker_command(Some("".into()), podman()),
+            ("podman".into(), DockerSource::Global)
+        );
+    }
+
+    #[test]
+    fn test_run_options() {
+        let options = RunOptions {
+            name: Some("aaa"),
+            deattach: true,
+            image: "alpine",
+            mounts: vec!["type=bind,source=/src/aaa,target=/workspaces/aaa".to_string()],
+            env: vec!["A=1".to_string()],
+            workdir: Some("/workspaces/aaa"),
+            args: vec!["sleep", "infinity"],
+            ..Default::default()
+        };
+        assert_eq!(
+            options.build(),
+            [
+                "run",
+                "--name",
+                "aaa",
+                "-d",
+                "--mount",
+                "type=bind,source=/src/aaa,target=/workspaces/aaa",
+                "-e",
+                "A=1",
+                "-w",
+                "/workspaces/aaa",
+                "alpine",
+                "sleep",
+                "infinity"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_options_ephemeral() {
+        let options = RunOptions {
+            remove: true,
+            interactive: true,
+            tty: true,
+            image: "alpine",
+            args: vec!["true"],
+            ..Default::default()
+        };
+        assert_eq!(
+            options.build(),
+            ["run", "--rm", "-i", "-t", "alpine", "true"]
+        );
+    }
+
+    #[test]
+    fn test_exec_options() {
+        let options = ExecOptions {
+            container: "aaa",
+            env: vec!["A=1".to_string()],
+            workdir: Some("/workspaces/aaa"),
+            args: vec!["/bin/zsh"],
+            ..Default::default()
+        };
+        assert_eq!(
+            options.build(),
+            [
+                "exec",
+                "-it",
+                "-e",
+                "A=1",
+                "-w",
+                "/workspaces/aaa",
+                "aaa",
+                "/bin/zsh"
+            ]
+        );
+    }
+
     mock! {
         pub DockerClient {}
 
         impl DockerClient for DockerClient {
-            fn list_containers(&self, project_name: &str) -> Result<Vec<ContainerSummary>>;
-            fn list_images(&self, project_name: &str) -> Result<Vec<ImageSummary>>;
-            fn build_image(&self, project_name: &str, dockerfile: &str) -> Result<()>;
+            fn list_containers(&self, project_id: &str) -> Result<Vec<ContainerSummary>>;
+            fn list_images(&self, project_id: &str) -> Result<Vec<ImageSummary>>;
+            fn list_all_containers(&self, size: bool) -> Result<Vec<ContainerSummary>>;
+            fn list_all_images(&self) -> Result<Vec<ImageSummary>>;
+            fn build_image<'a>(&self, tag: &str, dockerfile: &str, context: &Path, labels: HashMap<&'a str, &'a str>) -> Result<()>;
             fn start_container(&self, name: &str) -> Result<()>;
             fn stop_container(&self, name: &str) -> Result<()>;
-            fn run<'a>(&self, name: &str, image_name: &str, deattach: bool, args: Vec<&'a str>) -> Result<()>;
-            fn exec<'a>(&self, contain_name: &str, cmd: &[&'a str]) -> Result<()>;
+            fn remove_container(&self, id: &str) -> Result<()>;
+            fn remove_image(&self, id: &str) -> Result<()>;
+            fn run<'a>(&self, options: &RunOptions<'a>) -> Result<()>;
+            fn exec<'a>(&self, options: &ExecOptions<'a>) -> Result<()>;
+            fn logs(&self, name: &str, options: &LogOptions) -> Result<()>;
+            fn upload(&self, name: &str, dir: &str, archive: Vec<u8>) -> Result<()>;
+            fn download(&self, name: &str, path: &str) -> Result<Vec<u8>>;
+            fn is_dir(&self, name: &str, path: &str) -> Result<bool>;
+            fn host_info(&self) -> Result<HostInfo>;
         }
     }
 }
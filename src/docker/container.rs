@@ -1,43 +1,96 @@
 use bollard::models::ContainerSummary;
 
-use super::client::DockerClient;
+use super::{
+    agent::{Proxy, Relay, SshAgent},
+    client::{
+        DockerClient, ExecOptions, RunOptions, CONFIG_HASH_KEY, NAME_KEY, PROJECT_KEY, ROOT_KEY,
+    },
+    git::{Bridge, GitConfig},
+    gpg::GpgAgent,
+    resources,
+    state::{alive, FileStore, MemoryStore, State, StateStore},
+};
 use crate::{
-    docker::{client::new_client, image::new_image, Image},
-    project::{Config, Project},
+    config,
+    docker::{
+        archive,
+        client::{dry_run, new_client, LogOptions},
+        image::new_image,
+        Image,
+    },
+    error::DevspaceError,
+    project::{step_name, HostRequirements, Project},
+};
+use anyhow::{anyhow, Context, Result};
+use chrono::Local;
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
 };
-use anyhow::{anyhow, Result};
-use std::fmt;
+use tracing::{info, info_span, warn};
+
+pub const POST_CREATE_COMMAND: &str = "postCreateCommand";
 
 pub struct Container<'a> {
     name: &'a str,
-    config: &'a Config,
+    project: &'a Project,
     summary: Option<ContainerSummary>,
     client: Box<dyn DockerClient>,
+    store: Box<dyn StateStore>,
+    ssh_agent: Option<SshAgent>,
+    git: Option<GitConfig>,
+    /// forward the gpg agent of the host, looked up when creating the container
+    forward_gpg_agent: bool,
+    /// fail instead of warning when the docker host is short of the host requirements
+    enforce_host_requirements: bool,
 }
 
 impl<'a> Container<'a> {
     fn new(
-        name: &'a str,
-        config: &'a Config,
+        project: &'a Project,
         client: Box<dyn DockerClient>,
+        store: Box<dyn StateStore>,
     ) -> Result<Container<'a>> {
-        let containers = client.list_containers(name)?;
-
-        match containers.len() {
-            0 => Ok(Container {
-                name,
-                config,
-                summary: None,
-                client,
-            }),
-            1 => Ok(Container {
-                name,
-                config,
-                summary: Some(containers[0].clone()),
-                client,
-            }),
-            _ => Err(anyhow!("Multiple containers with the same name")),
-        }
+        let containers = client.list_containers(&project.id)?;
+
+        let summary = match containers.len() {
+            0 => None,
+            1 => Some(containers[0].clone()),
+            count => {
+                return Err(DevspaceError::ContainerConflict {
+                    project: project.name.clone(),
+                    count,
+                }
+                .into())
+            }
+        };
+        Ok(Container {
+            name: &project.container_name,
+            project,
+            summary,
+            client,
+            store,
+            ssh_agent: None,
+            git: None,
+            forward_gpg_agent: false,
+            enforce_host_requirements: false,
+        })
+    }
+
+    /// share the agents and git settings of the host and check its resources as the global
+    /// config asks, the git settings and gpg agent are only looked up to create a container
+    pub fn share_host(mut self, global: &config::Config) -> Self {
+        self.enforce_host_requirements = global.enforce_host_requirements;
+        self.ssh_agent = SshAgent::from_host(global.forward_ssh_agent);
+        self.git = global
+            .share_git_config
+            .unwrap_or(true)
+            .then(|| GitConfig::new(global.forward_git_credentials, global.forward_gpg_agent));
+        self.forward_gpg_agent = global.forward_gpg_agent;
+        self
     }
 
     pub fn existing(&self) -> bool {
@@ -50,29 +103,288 @@ impl<'a> Container<'a> {
             .is_some_and(|s| s.state.as_ref().is_some_and(|s| s == "running"))
     }
 
+    pub fn summary(&self) -> Option<&ContainerSummary> {
+        self.summary.as_ref()
+    }
+
+    /// whether the container was created from a different config than the current one,
+    /// None if the container does not exist or carries no config hash
+    pub fn config_drift(&self) -> Option<bool> {
+        let labels = self.summary.as_ref()?.labels.as_ref()?;
+        labels
+            .get(CONFIG_HASH_KEY)
+            .map(|h| h != &self.project.config.hash)
+    }
+
+    pub fn image(&self) -> Result<Box<dyn Image>> {
+        new_image(self.project, self.client.as_ref())
+    }
+
+    pub fn state(&self) -> Result<State> {
+        self.store.load(&self.project.id)
+    }
+
     pub fn setup(&self) -> Result<()> {
-        let mut image: Box<dyn Image> =
-            new_image(self.name, &self.config.image_source, self.client.as_ref())?;
+        self.check_host_requirements()?;
+        let mut image = self.image()?;
 
         if !image.existing() {
-            println!("image does not exist, building...");
+            info!("image does not exist, building...");
             image.build(self.client.as_ref())?;
         }
 
-        self.client
-            .run(self.name, image.name(), true, vec!["sleep", "infinity"])?;
+        let root = self.project.root.display().to_string();
+        let labels = HashMap::from([
+            (PROJECT_KEY, self.project.id.as_str()),
+            (NAME_KEY, self.project.name.as_str()),
+            (CONFIG_HASH_KEY, self.project.config.hash.as_str()),
+            (ROOT_KEY, root.as_str()),
+        ]);
+        let gpg_agent = self.forward_gpg_agent.then(GpgAgent::from_host).flatten();
+        let mut mounts = self.project.mounts();
+        let socket_dir = self.store.socket_dir(&self.project.id)?;
+        let shared = (self.ssh_agent.as_ref().and_then(|a| a.mount(&socket_dir)))
+            .into_iter()
+            .chain(self.git.as_ref().and_then(|g| g.mount(&socket_dir)));
+        // the agent and the credentials share the mount of the socket dir
+        for mount in shared {
+            if !mounts.contains(&mount) {
+                mounts.push(mount);
+            }
+        }
+        mounts.extend(gpg_agent.as_ref().map(|a| a.mount()));
+        let limits = &self.project.config.customizations.devspace;
+        let options = RunOptions {
+            name: Some(self.name),
+            deattach: true,
+            image: image.name(),
+            labels,
+            mounts,
+            env: self.project.container_env(),
+            workdir: Some(&self.project.workspace_folder),
+            cpus: limits.cpus,
+            memory: limits.memory.map(|m| m.0),
+            pids_limit: limits.pids_limit,
+            args: vec!["sleep", "infinity"],
+            ..Default::default()
+        };
+        info_span!("run", container = self.name).in_scope(|| self.client.run(&options))?;
 
-        if let Some(command) = &self.config.post_create_command {
-            println!("running post-create command...");
-            let c = command.iter().map(|s| s.as_str()).collect::<Vec<_>>();
-            self.client.exec(self.name, &c)?;
+        let now = Local::now().timestamp();
+        self.store.update(&self.project.id, &mut |state| {
+            // a new container, only the sessions of other processes carry over
+            *state = State {
+                sessions: std::mem::take(&mut state.sessions),
+                idle_since: Some(now),
+                ..Default::default()
+            };
+        })?;
+
+        if let Some(git) = &self.git {
+            info!("copying git config...");
+            let script = git.script(&git.host_settings(), &self.project.workspace_folder);
+            self.client
+                .exec(&self.exec_options(&["sh", "-c", &script], None))?;
+        }
+
+        if let Some(agent) = &gpg_agent {
+            info!("importing gpg public keys...");
+            let (dir, keyring) = agent.keyring()?;
+            self.client.upload(self.name, dir, keyring)?;
+            self.client
+                .exec(&self.exec_options(&["sh", "-c", &agent.script()], None))?;
+        }
+
+        if let Some(command) = &self.project.config.post_create_command {
+            info!("running post-create command...");
+            for (name, args) in command.commands() {
+                self.lifecycle(&step_name(POST_CREATE_COMMAND, name), &args)?;
+            }
+            self.store.update(&self.project.id, &mut |state| {
+                state.lifecycle.push(POST_CREATE_COMMAND.to_string())
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// warn about or refuse a docker host short of the hostRequirements of the config
+    fn check_host_requirements(&self) -> Result<()> {
+        let requirements = &self.project.config.host_requirements;
+        if *requirements == HostRequirements::default() {
+            return Ok(());
+        }
+        let host = self.client.host_info()?;
+        let free_storage = requirements
+            .storage
+            .and(host.root_dir.as_deref())
+            .and_then(|dir| resources::free_space(Path::new(dir)));
+        let problems = resources::unmet(requirements, &host, free_storage);
+        if problems.is_empty() {
+            return Ok(());
+        }
+        if self.enforce_host_requirements {
+            return Err(DevspaceError::HostRequirementsUnmet { problems }.into());
+        }
+        for problem in problems {
+            warn!("host requirements: {}", problem);
         }
+        Ok(())
+    }
 
+    /// record a session of this process in the container, to be ended with `detach`
+    pub fn attach(&self) -> Result<()> {
+        let pid = std::process::id();
+        self.store.update(&self.project.id, &mut |state| {
+            state.prune_sessions(alive);
+            state.sessions.push(pid);
+            state.idle_since = None;
+        })?;
         Ok(())
     }
 
+    /// end a session of this process, returns the number of sessions still open
+    pub fn detach(&self) -> Result<usize> {
+        let pid = std::process::id();
+        let now = Local::now().timestamp();
+        let state = self.store.update(&self.project.id, &mut |state| {
+            if let Some(i) = state.sessions.iter().position(|p| *p == pid) {
+                state.sessions.remove(i);
+            }
+            state.prune_sessions(alive);
+            if state.sessions.is_empty() {
+                state.idle_since = Some(now);
+            }
+        })?;
+        Ok(state.sessions.len())
+    }
+
+    /// run a command in the workspace folder with the remote environment of the config
     pub fn exec(&self, cmd: &[&str]) -> Result<()> {
-        self.client.exec(self.name, cmd)
+        let _span = info_span!("exec", container = self.name).entered();
+        let _services = self.host_services()?;
+        self.client.exec(&self.exec_options(cmd, None))
+    }
+
+    /// run a lifecycle command like `exec`, recording its output in the lifecycle log
+    fn lifecycle(&self, step: &str, cmd: &[&str]) -> Result<()> {
+        let _span = info_span!("lifecycle", step, container = self.name).entered();
+        let log = self.lifecycle_log()?;
+        let mut f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log)
+            .with_context(|| format!("failed to open log file {:?}", log))?;
+        writeln!(
+            f,
+            "==> {} {}: {}",
+            Local::now().to_rfc3339(),
+            step,
+            cmd.join(" ")
+        )?;
+        let _services = self.host_services()?;
+        self.client.exec(&self.exec_options(cmd, Some(&log)))
+    }
+
+    /// host side of the forwarded agents and credentials, serving while they are kept
+    fn host_services(&self) -> Result<(Option<Relay>, Option<Proxy>, Option<Bridge>)> {
+        let (relay, proxy) = match self.ssh_agent() {
+            Some(agent) => (
+                agent.relay(self.name)?,
+                agent.proxy(&self.store.socket_dir(&self.project.id)?)?,
+            ),
+            None => (None, None),
+        };
+        let bridge = match &self.git {
+            Some(git) => git.bridge(&self.store.socket_dir(&self.project.id)?)?,
+            None => None,
+        };
+        Ok((relay, proxy, bridge))
+    }
+
+    /// the forwarded ssh agent, None if forwarding is off or the container was created
+    /// without the mount the agent needs
+    fn ssh_agent(&self) -> Option<&SshAgent> {
+        let agent = self.ssh_agent.as_ref()?;
+        let Some(target) = agent.target() else {
+            return Some(agent);
+        };
+        // a container created by this process has the mounts of the current config
+        let Some(summary) = &self.summary else {
+            return Some(agent);
+        };
+        let mounts = summary.mounts.as_deref().unwrap_or_default();
+        mounts
+            .iter()
+            .any(|m| m.destination.as_deref() == Some(target))
+            .then_some(agent)
+    }
+
+    fn exec_options<'b>(&'b self, cmd: &[&'b str], log: Option<&'b Path>) -> ExecOptions<'b> {
+        let mut env = self.project.remote_env();
+        env.extend(self.ssh_agent().map(|a| a.env()));
+        env.extend(self.git.as_ref().and_then(|g| g.env()));
+        ExecOptions {
+            container: self.name,
+            env,
+            workdir: Some(&self.project.workspace_folder),
+            args: cmd.to_vec(),
+            log,
+        }
+    }
+
+    /// file with the output of the lifecycle commands run by devspace
+    pub fn lifecycle_log(&self) -> Result<PathBuf> {
+        self.store.log_file(&self.project.id)
+    }
+
+    /// show the output of the container's main process
+    pub fn logs(&self, options: &LogOptions) -> Result<()> {
+        if !self.existing() {
+            return Err(anyhow!("container {} does not exist", self.name));
+        }
+        self.client.logs(self.name, options)
+    }
+
+    /// copy a host file or directory to `dst` in the container, relative paths are relative
+    /// to the workspace folder, an existing directory `dst` receives a copy of `src`
+    pub fn copy_to(&self, src: &Path, dst: &str) -> Result<()> {
+        let src = std::fs::canonicalize(src).with_context(|| format!("can not read {:?}", src))?;
+        let dst = self.container_path(dst);
+        let (dir, name) = if dst.ends_with('/') || self.client.is_dir(self.name, &dst)? {
+            (dst.as_str(), base_name(&src)?)
+        } else {
+            let dst = Path::new(&dst);
+            let dir = dst.parent().and_then(|p| p.to_str()).unwrap_or("/");
+            (dir, base_name(dst)?)
+        };
+        let archive = archive::pack(&src, &name)?;
+        self.client.upload(self.name, dir, archive)
+    }
+
+    /// copy a file or directory of the container to `dst` on the host, an existing directory
+    /// `dst` receives a copy of `src`
+    pub fn copy_from(&self, src: &str, dst: &Path) -> Result<()> {
+        let src = self.container_path(src);
+        let archive = self.client.download(self.name, &src)?;
+        let dst = if dst.is_dir() {
+            dst.join(base_name(Path::new(&src))?)
+        } else {
+            dst.to_path_buf()
+        };
+        archive::unpack(&archive, &dst)
+    }
+
+    fn container_path(&self, path: &str) -> String {
+        if path.starts_with('/') {
+            path.to_string()
+        } else {
+            format!(
+                "{}/{}",
+                self.project.workspace_folder.trim_end_matches('/'),
+                path
+            )
+        }
     }
 
     pub fn start(&self) -> Result<()> {
@@ -84,11 +396,35 @@ impl<'a> Container<'a> {
     }
 }
 
+/// name and project root of the containers of all projects
+pub fn all_containers() -> Result<Vec<(String, PathBuf)>> {
+    let containers = new_client()?.list_all_containers(false)?;
+    Ok(containers
+        .into_iter()
+        .filter_map(|c| {
+            let name = c.names?.first()?.trim_start_matches('/').to_string();
+            let root = c.labels?.get(ROOT_KEY)?.into();
+            Some((name, root))
+        })
+        .collect())
+}
+
+fn base_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow!("can not copy {:?}", path))
+}
+
 impl<'a> TryFrom<&'a Project> for Container<'a> {
     type Error = anyhow::Error;
 
     fn try_from(p: &'a Project) -> Result<Self> {
-        Container::new(&p.name, &p.config, new_client()?)
+        let store: Box<dyn StateStore> = if dry_run() {
+            Box::<MemoryStore>::default()
+        } else {
+            Box::new(FileStore::xdg()?)
+        };
+        Container::new(p, new_client()?, store)
     }
 }
 
@@ -96,7 +432,7 @@ impl fmt::Debug for Container<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Container")
             .field("name", &self.name)
-            .field("config", &self.config)
+            .field("project", &self.project)
             .field("summary", &self.summary)
             .finish()
     }
@@ -105,7 +441,13 @@ impl fmt::Debug for Container<'_> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::docker::client::tests::MockDockerClient;
+    use crate::{
+        docker::{
+            client::{tests::MockDockerClient, HostInfo},
+            state::tests::MockStateStore,
+        },
+        project::tests::TmpProjectDir,
+    };
     use bollard::models::ContainerSummary;
 
     #[test]
@@ -121,10 +463,12 @@ mod tests {
 
         let project = Project {
             name: "aaa".to_string(),
+            container_name: "aaa".to_string(),
             ..Default::default()
         };
 
-        let container = Container::new(&project.name, &project.config, Box::new(client)).unwrap();
+        let container =
+            Container::new(&project, Box::new(client), Box::new(MockStateStore::new())).unwrap();
         assert!(container.existing());
         assert!(container.running());
         assert_eq!(container.name, "aaa");
@@ -143,10 +487,12 @@ mod tests {
 
         let project = Project {
             name: "bbb".to_string(),
+            container_name: "bbb".to_string(),
             ..Default::default()
         };
 
-        let container = Container::new(&project.name, &project.config, Box::new(client)).unwrap();
+        let container =
+            Container::new(&project, Box::new(client), Box::new(MockStateStore::new())).unwrap();
         assert!(container.existing());
         assert!(!container.running());
         assert_eq!(container.name, "bbb");
@@ -172,14 +518,17 @@ mod tests {
 
         let project = Project {
             name: "bbb".to_string(),
+            container_name: "bbb".to_string(),
             ..Default::default()
         };
 
-        let err = Container::new(&project.name, &project.config, Box::new(client)).unwrap_err();
-        assert_eq!(
-            format!("{:?}", err),
-            "Multiple containers with the same name"
-        );
+        let err = Container::new(&project, Box::new(client), Box::new(MockStateStore::new()))
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DevspaceError>(),
+            Some(DevspaceError::ContainerConflict { count: 2, .. })
+        ));
+        assert_eq!(err.to_string(), "found 2 containers for project bbb");
     }
 
     #[test]
@@ -188,13 +537,318 @@ mod tests {
         client.expect_list_containers().returning(|_| Ok(vec![]));
 
         let project = Project {
-            name: "bbb".to_string(),
+            name: "ccc".to_string(),
+            container_name: "ccc".to_string(),
             ..Default::default()
         };
 
-        let container = Container::new("ccc", &project.config, Box::new(client)).unwrap();
+        let container =
+            Container::new(&project, Box::new(client), Box::new(MockStateStore::new())).unwrap();
         assert!(!container.existing());
         assert!(!container.running());
         assert_eq!(container.name, "ccc");
     }
+
+    #[test]
+    fn test_setup() {
+        let json = r#"{
+            "image": "alpine",
+            "postCreateCommand": ["echo", "hello"],
+            "mounts": [{ "type": "volume", "source": "cache", "target": "/cache" }],
+            "containerEnv": { "A": "1" },
+            "remoteEnv": { "B": "${containerWorkspaceFolder}", "C": null }
+        }"#;
+        let tmp_project = TmpProjectDir::new("ddd").devcontainer_json(json);
+        let project = Project::try_from(&tmp_project.root).unwrap();
+
+        let mut client = MockDockerClient::new();
+        client.expect_list_containers().returning(|_| Ok(vec![]));
+        let (name, id) = (project.container_name.clone(), project.id.clone());
+        let root = project.root.display().to_string();
+        client
+            .expect_run()
+            .withf(move |o| {
+                o.name == Some(name.as_str())
+                    && o.image == "alpine"
+                    && o.labels.get(PROJECT_KEY) == Some(&id.as_str())
+                    && o.labels.get(NAME_KEY) == Some(&"ddd")
+                    && o.deattach
+                    && o.mounts
+                        == [
+                            format!("type=bind,source={},target=/workspaces/ddd", root),
+                            "type=volume,source=cache,target=/cache".to_string(),
+                        ]
+                    && o.env == ["A=1"]
+                    && o.workdir == Some("/workspaces/ddd")
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        client
+            .expect_exec()
+            .withf(|o| {
+                o.args == ["echo", "hello"]
+                    && o.env == ["B=/workspaces/ddd"]
+                    && o.workdir == Some("/workspaces/ddd")
+                    && o.log.is_some()
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let mut store = MockStateStore::new();
+        let log = tmp_project.root.join("lifecycle.log");
+        let l = log.clone();
+        store.expect_log_file().returning(move |_| Ok(l.clone()));
+        let dir = tmp_project.root.clone();
+        store
+            .expect_socket_dir()
+            .returning(move |_| Ok(dir.clone()));
+        // another process attached while the container was created
+        store.expect_load().returning(|_| {
+            Ok(State {
+                sessions: vec![42],
+                ..Default::default()
+            })
+        });
+        store
+            .expect_save()
+            .withf(|_, state| state.lifecycle.is_empty() && state.sessions == [42])
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let id = project.id.clone();
+        store
+            .expect_save()
+            .withf(move |project_id, state| {
+                project_id == id && state.lifecycle == [POST_CREATE_COMMAND]
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let container = Container::new(&project, Box::new(client), Box::new(store)).unwrap();
+        container.setup().unwrap();
+        let content = std::fs::read_to_string(&log).unwrap();
+        assert!(content.starts_with("==> "));
+        assert!(content.ends_with(" postCreateCommand: echo hello\n"));
+    }
+
+    #[test]
+    fn test_forward_ssh_agent() {
+        let tmp_project = TmpProjectDir::new("eee").devcontainer_json(r#"{ "image": "alpine" }"#);
+        let project = Project::try_from(&tmp_project.root).unwrap();
+        let socket_dir = tmp_project.root.join("sockets");
+        std::fs::create_dir(&socket_dir).unwrap();
+
+        let mut client = MockDockerClient::new();
+        client.expect_list_containers().returning(|_| Ok(vec![]));
+        let mount = format!(
+            "type=bind,source={},target=/tmp/devspace/host",
+            socket_dir.display()
+        );
+        client
+            .expect_run()
+            .withf(move |o| o.mounts.last() == Some(&mount))
+            .times(1)
+            .returning(|_| Ok(()));
+        let name = format!("ssh-agent-{}.sock", std::process::id());
+        let proxy = socket_dir.join(&name);
+        let env = format!("SSH_AUTH_SOCK=/tmp/devspace/host/{}", name);
+        client
+            .expect_exec()
+            .withf(move |o| o.env == [env.clone()] && proxy.exists())
+            .times(1)
+            .returning(|_| Ok(()));
+        let mut store = MockStateStore::new();
+        store.expect_load().returning(|_| Ok(State::default()));
+        store.expect_save().returning(|_, _| Ok(()));
+        let dir = socket_dir.clone();
+        store
+            .expect_socket_dir()
+            .returning(move |_| Ok(dir.clone()));
+
+        let mut container = Container::new(&project, Box::new(client), Box::new(store)).unwrap();
+        container.ssh_agent = SshAgent::new(
+            config::Forwarding::Mount,
+            Some("/tmp/agent.sock".into()),
+            "linux",
+        );
+        container.setup().unwrap();
+        container.exec(&["sh"]).unwrap();
+        assert!(!socket_dir.join(name).exists());
+    }
+
+    #[test]
+    fn test_forward_ssh_agent_without_mount() {
+        let tmp_project = TmpProjectDir::new("eee").devcontainer_json(r#"{ "image": "alpine" }"#);
+        let project = Project::try_from(&tmp_project.root).unwrap();
+
+        // created before the agent was forwarded
+        let mut client = MockDockerClient::new();
+        client.expect_list_containers().returning(|_| {
+            Ok(vec![ContainerSummary {
+                state: Some("running".to_string()),
+                mounts: Some(vec![]),
+                ..Default::default()
+            }])
+        });
+        client
+            .expect_exec()
+            .withf(|o| o.env.is_empty())
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let mut container =
+            Container::new(&project, Box::new(client), Box::new(MockStateStore::new())).unwrap();
+        container.ssh_agent = SshAgent::new(
+            config::Forwarding::Mount,
+            Some("/tmp/agent.sock".into()),
+            "linux",
+        );
+        container.exec(&["sh"]).unwrap();
+    }
+
+    #[test]
+    fn test_host_requirements() {
+        let json = r#"{ "image": "alpine", "hostRequirements": { "cpus": 8 } }"#;
+        let tmp_project = TmpProjectDir::new("fff").devcontainer_json(json);
+        let project = Project::try_from(&tmp_project.root).unwrap();
+
+        let mut client = MockDockerClient::new();
+        client.expect_list_containers().returning(|_| Ok(vec![]));
+        client.expect_host_info().returning(|| {
+            Ok(HostInfo {
+                cpus: 2,
+                ..Default::default()
+            })
+        });
+        client.expect_run().never();
+
+        let mut container =
+            Container::new(&project, Box::new(client), Box::new(MockStateStore::new())).unwrap();
+        container.enforce_host_requirements = true;
+        let err = container.setup().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DevspaceError>(),
+            Some(DevspaceError::HostRequirementsUnmet { problems }) if problems.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_host_requirements_warn() {
+        let json = r#"{ "image": "alpine", "hostRequirements": { "cpus": 8 } }"#;
+        let tmp_project = TmpProjectDir::new("fff").devcontainer_json(json);
+        let project = Project::try_from(&tmp_project.root).unwrap();
+
+        let mut client = MockDockerClient::new();
+        client.expect_list_containers().returning(|_| Ok(vec![]));
+        client.expect_host_info().returning(|| {
+            Ok(HostInfo {
+                cpus: 2,
+                ..Default::default()
+            })
+        });
+        client.expect_run().times(1).returning(|_| Ok(()));
+        let mut store = MockStateStore::new();
+        store.expect_load().returning(|_| Ok(State::default()));
+        store.expect_save().returning(|_, _| Ok(()));
+        let dir = tmp_project.root.clone();
+        store
+            .expect_socket_dir()
+            .returning(move |_| Ok(dir.clone()));
+
+        let container = Container::new(&project, Box::new(client), Box::new(store)).unwrap();
+        container.setup().unwrap();
+    }
+
+    #[test]
+    fn test_sessions() {
+        let project = Project {
+            id: "0123".to_string(),
+            ..Default::default()
+        };
+        let mut client = MockDockerClient::new();
+        client.expect_list_containers().returning(|_| Ok(vec![]));
+        let container =
+            Container::new(&project, Box::new(client), Box::<MemoryStore>::default()).unwrap();
+
+        container.attach().unwrap();
+        container.attach().unwrap();
+        assert_eq!(container.state().unwrap().idle_since, None);
+        assert_eq!(container.detach().unwrap(), 1);
+        assert_eq!(container.state().unwrap().idle_since, None);
+        assert_eq!(container.detach().unwrap(), 0);
+        assert!(container.state().unwrap().idle_since.is_some());
+    }
+
+    #[test]
+    fn test_copy_to() {
+        let tmp_project = TmpProjectDir::new("eee").devcontainer_json(r#"{ "image": "alpine" }"#);
+        let project = Project::try_from(&tmp_project.root).unwrap();
+        let src = tmp_project.root.join("notes.txt");
+        std::fs::write(&src, "hello").unwrap();
+
+        let mut client = MockDockerClient::new();
+        client.expect_list_containers().returning(|_| Ok(vec![]));
+        client
+            .expect_is_dir()
+            .withf(|_, path| path == "/workspaces/eee/docs")
+            .returning(|_, _| Ok(true));
+        client
+            .expect_is_dir()
+            .withf(|_, path| path == "/tmp/renamed.txt")
+            .returning(|_, _| Ok(false));
+        let entry_name = |archive: &[u8]| {
+            let mut archive = tar::Archive::new(archive);
+            let mut entries = archive.entries().unwrap();
+            let name = entries
+                .next()
+                .unwrap()
+                .unwrap()
+                .path()
+                .unwrap()
+                .display()
+                .to_string();
+            name
+        };
+        client
+            .expect_upload()
+            .withf(move |_, dir, archive| {
+                dir == "/workspaces/eee/docs" && entry_name(archive) == "notes.txt"
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        client
+            .expect_upload()
+            .withf(move |_, dir, archive| dir == "/tmp" && entry_name(archive) == "renamed.txt")
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let container =
+            Container::new(&project, Box::new(client), Box::new(MockStateStore::new())).unwrap();
+        container.copy_to(&src, "docs").unwrap();
+        container.copy_to(&src, "/tmp/renamed.txt").unwrap();
+    }
+
+    #[test]
+    fn test_copy_from() {
+        let tmp_project = TmpProjectDir::new("fff").devcontainer_json(r#"{ "image": "alpine" }"#);
+        let project = Project::try_from(&tmp_project.root).unwrap();
+        let file = tmp_project.root.join("a.txt");
+        std::fs::write(&file, "hello").unwrap();
+        let archive = archive::pack(&file, "a.txt").unwrap();
+
+        let mut client = MockDockerClient::new();
+        client.expect_list_containers().returning(|_| Ok(vec![]));
+        client
+            .expect_download()
+            .withf(|_, path| path == "/workspaces/fff/a.txt")
+            .returning(move |_, _| Ok(archive.clone()));
+
+        let container =
+            Container::new(&project, Box::new(client), Box::new(MockStateStore::new())).unwrap();
+        let out = tmp_project.root.join("out");
+        std::fs::create_dir(&out).unwrap();
+        container.copy_from("a.txt", &out).unwrap();
+        assert_eq!(std::fs::read_to_string(out.join("a.txt")).unwrap(), "hello");
+        container.copy_from("a.txt", &out.join("b.txt")).unwrap();
+        assert_eq!(std::fs::read_to_string(out.join("b.txt")).unwrap(), "hello");
+    }
 }
@@ -0,0 +1,216 @@
+use super::{
+    client::{
+        BuildOptions, DockerClient, ExecOptions, HostInfo, LogOptions, RunOptions, PROJECT_KEY,
+    },
+    process::quote,
+};
+use anyhow::Result;
+use bollard::models::{ContainerSummary, ImageSummary};
+use std::{cell::RefCell, collections::HashMap, path::Path};
+
+/// size of an empty tar archive, two zero blocks
+const EMPTY_ARCHIVE_LEN: usize = 1024;
+
+/// client printing the docker commands equivalent to its mutations instead of running them,
+/// reads go to `inner` if there is one and find nothing otherwise
+pub struct DryRunClient {
+    inner: Option<Box<dyn DockerClient>>,
+    plan: RefCell<Vec<String>>,
+    /// images "built" so far, so the following steps find them
+    built: RefCell<Vec<ImageSummary>>,
+}
+
+impl DryRunClient {
+    pub fn new(inner: Option<Box<dyn DockerClient>>) -> Self {
+        DryRunClient {
+            inner,
+            plan: Default::default(),
+            built: Default::default(),
+        }
+    }
+
+    /// commands recorded so far, in order
+    #[cfg(test)]
+    pub fn plan(&self) -> Vec<String> {
+        self.plan.borrow().clone()
+    }
+
+    fn record<S: AsRef<str>>(&self, args: &[S]) {
+        let mut line = vec!["docker".to_string()];
+        line.extend(args.iter().map(|a| quote(a.as_ref())));
+        let line = line.join(" ");
+        println!("{}", line);
+        self.plan.borrow_mut().push(line);
+    }
+
+    fn read<T: Default>(&self, f: impl FnOnce(&dyn DockerClient) -> Result<T>) -> Result<T> {
+        match &self.inner {
+            Some(inner) => f(inner.as_ref()),
+            None => Ok(T::default()),
+        }
+    }
+}
+
+impl DockerClient for DryRunClient {
+    fn list_containers(&self, project_id: &str) -> Result<Vec<ContainerSummary>> {
+        self.read(|c| c.list_containers(project_id))
+    }
+
+    fn list_images(&self, project_id: &str) -> Result<Vec<ImageSummary>> {
+        let mut images = self.read(|c| c.list_images(project_id))?;
+        images.extend(
+            self.built
+                .borrow()
+                .iter()
+                .filter(|i| i.labels.get(PROJECT_KEY).map(|p| p.as_str()) == Some(project_id))
+                .cloned(),
+        );
+        Ok(images)
+    }
+
+    fn list_all_containers(&self, size: bool) -> Result<Vec<ContainerSummary>> {
+        self.read(|c| c.list_all_containers(size))
+    }
+
+    fn list_all_images(&self) -> Result<Vec<ImageSummary>> {
+        self.read(|c| c.list_all_images())
+    }
+
+    fn build_image(
+        &self,
+        tag: &str,
+        dockerfile: &str,
+        context: &Path,
+        labels: HashMap<&str, &str>,
+    ) -> Result<()> {
+        let image = ImageSummary {
+            repo_tags: vec![tag.to_string()],
+            labels: labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+        self.record(&BuildOptions::new(tag, dockerfile, context, labels).build());
+        self.built.borrow_mut().push(image);
+        Ok(())
+    }
+
+    fn start_container(&self, name: &str) -> Result<()> {
+        self.record(&["start", name]);
+        Ok(())
+    }
+
+    fn stop_container(&self, name: &str) -> Result<()> {
+        self.record(&["stop", name]);
+        Ok(())
+    }
+
+    fn remove_container(&self, id: &str) -> Result<()> {
+        self.record(&["rm", "-f", id]);
+        Ok(())
+    }
+
+    fn remove_image(&self, id: &str) -> Result<()> {
+        self.record(&["rmi", id]);
+        Ok(())
+    }
+
+    fn run(&self, options: &RunOptions) -> Result<()> {
+        self.record(&options.build());
+        Ok(())
+    }
+
+    fn exec(&self, options: &ExecOptions) -> Result<()> {
+        self.record(&options.build());
+        Ok(())
+    }
+
+    fn logs(&self, name: &str, options: &LogOptions) -> Result<()> {
+        self.read(|c| c.logs(name, options))
+    }
+
+    fn upload(&self, name: &str, dir: &str, _: Vec<u8>) -> Result<()> {
+        self.record(&["cp", "-", &format!("{}:{}", name, dir)]);
+        Ok(())
+    }
+
+    fn download(&self, name: &str, path: &str) -> Result<Vec<u8>> {
+        // nothing may be written on the host either, so hand out an empty archive
+        self.record(&["cp", &format!("{}:{}", name, path), "-"]);
+        Ok(vec![0; EMPTY_ARCHIVE_LEN])
+    }
+
+    fn is_dir(&self, name: &str, path: &str) -> Result<bool> {
+        self.read(|c| c.is_dir(name, path))
+    }
+
+    fn host_info(&self) -> Result<HostInfo> {
+        self.read(|c| c.host_info())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker::client::tests::MockDockerClient;
+
+    #[test]
+    fn test_plan() {
+        let client = DryRunClient::new(None);
+        client
+            .build_image(
+                "aaa:latest",
+                "/src/aaa/Dockerfile",
+                Path::new("/src/aaa"),
+                HashMap::from([(PROJECT_KEY, "0123")]),
+            )
+            .unwrap();
+        assert_eq!(client.list_images("0123").unwrap().len(), 1);
+        assert!(client.list_images("4567").unwrap().is_empty());
+
+        client
+            .run(&RunOptions {
+                name: Some("aaa"),
+                deattach: true,
+                image: "aaa:latest",
+                args: vec!["sleep", "infinity"],
+                ..Default::default()
+            })
+            .unwrap();
+        client
+            .exec(&ExecOptions {
+                container: "aaa",
+                args: vec!["sh", "-c", "echo hello"],
+                ..Default::default()
+            })
+            .unwrap();
+        client.stop_container("aaa").unwrap();
+
+        assert_eq!(
+            client.plan(),
+            [
+                "docker build -t aaa:latest -f /src/aaa/Dockerfile --label ds_project=0123 /src/aaa",
+                "docker run --name aaa -d aaa:latest sleep infinity",
+                "docker exec -it aaa sh -c 'echo hello'",
+                "docker stop aaa",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reads_go_to_inner() {
+        let mut inner = MockDockerClient::new();
+        inner.expect_list_containers().times(1).returning(|_| {
+            Ok(vec![ContainerSummary {
+                id: Some("1".to_string()),
+                ..Default::default()
+            }])
+        });
+        inner.expect_run().never();
+
+        let client = DryRunClient::new(Some(Box::new(inner)));
+        assert_eq!(client.list_containers("0123").unwrap().len(), 1);
+        client.run(&RunOptions::default()).unwrap();
+    }
+}
@@ -0,0 +1,104 @@
+use super::{
+    client::{new_client, DockerClient, RunOptions},
+    image::new_image,
+};
+use crate::{error::DevspaceError, project::Project};
+use anyhow::Result;
+use tracing::{info, info_span};
+
+/// throwaway containers from the project image, independent of the project container
+pub struct Ephemeral<'a> {
+    project: &'a Project,
+    client: Box<dyn DockerClient>,
+}
+
+impl<'a> Ephemeral<'a> {
+    pub fn new(project: &'a Project) -> Result<Self> {
+        Ok(Ephemeral::with_client(project, new_client()?))
+    }
+
+    fn with_client(project: &'a Project, client: Box<dyn DockerClient>) -> Self {
+        Ephemeral { project, client }
+    }
+
+    /// run `cmd` in a new container removed after it exits, building the image if needed,
+    /// a failing command is reported as `DevspaceError::UserCommandFailed` with its exit code
+    pub fn run(&self, cmd: &[&str], tty: bool) -> Result<()> {
+        let mut image = new_image(self.project, self.client.as_ref())?;
+        if !image.existing() {
+            info!("image does not exist, building...");
+            image.build(self.client.as_ref())?;
+        }
+
+        // the command takes the place of the remote user commands, so it gets both envs
+        let mut env = self.project.container_env();
+        env.extend(self.project.remote_env());
+        let limits = &self.project.config.customizations.devspace;
+        let options = RunOptions {
+            remove: true,
+            interactive: true,
+            tty,
+            image: image.name(),
+            mounts: self.project.mounts(),
+            env,
+            workdir: Some(&self.project.workspace_folder),
+            cpus: limits.cpus,
+            memory: limits.memory.map(|m| m.0),
+            pids_limit: limits.pids_limit,
+            args: cmd.to_vec(),
+            ..Default::default()
+        };
+        let _span = info_span!("run", image = image.name()).entered();
+        self.client
+            .run(&options)
+            .map_err(DevspaceError::user_command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{docker::client::tests::MockDockerClient, project::tests::TmpProjectDir};
+
+    #[test]
+    fn test_run() {
+        let json = r#"{
+            "image": "alpine",
+            "containerEnv": { "A": "1" },
+            "remoteEnv": { "B": "2", "C": null }
+        }"#;
+        let tmp_project = TmpProjectDir::new("aaa").devcontainer_json(json);
+        let project = Project::try_from(&tmp_project.root).unwrap();
+        let workspace_mount = project.workspace_mount.clone();
+
+        let mut client = MockDockerClient::new();
+        client
+            .expect_run()
+            .withf(move |o| {
+                o.name.is_none()
+                    && o.remove
+                    && !o.deattach
+                    && o.labels.is_empty()
+                    && o.image == "alpine"
+                    && o.mounts == [workspace_mount.clone()]
+                    && o.env == ["A=1", "B=2"]
+                    && o.workdir == Some("/workspaces/aaa")
+                    && o.args == ["cargo", "test"]
+            })
+            .times(1)
+            .returning(|_| {
+                Err(DevspaceError::CommandFailed {
+                    command: "docker run".to_string(),
+                    code: Some(101),
+                }
+                .into())
+            });
+
+        let ephemeral = Ephemeral::with_client(&project, Box::new(client));
+        let err = ephemeral.run(&["cargo", "test"], false).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<DevspaceError>().unwrap().exit_code(),
+            101
+        );
+    }
+}
@@ -0,0 +1,993 @@
+//! in-process stand-in for the docker daemon and cli, so commands can be tested end to end
+//! without docker
+//!
+//! The daemon serves the engine API on a unix socket in a temporary directory. The cli is a
+//! shell script recording its arguments, the daemon replays them before answering the next
+//! request, so `docker run -d` creates a container visible to the following calls.
+
+use super::{archive, process::quote};
+use bollard::models::{ContainerSummary, ImageSummary};
+use serde_json::{json, Value};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+use tempfile::TempDir;
+
+/// resources the fake daemon reports for its host
+pub const FAKE_CPUS: u64 = 4;
+pub const FAKE_MEMORY: u64 = 8 << 30;
+
+thread_local! {
+    static INSTALLED: RefCell<Option<Endpoint>> = const { RefCell::new(None) };
+}
+
+/// what `new_client` and the state store use instead of the real docker on this thread, while
+/// the global config and the agents and git config of the host read as empty
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    pub socket: PathBuf,
+    pub cli: PathBuf,
+    pub state_dir: PathBuf,
+}
+
+/// the endpoint of the daemon installed on the current thread, if any
+pub fn installed() -> Option<Endpoint> {
+    INSTALLED.with(|i| i.borrow().clone())
+}
+
+pub struct FakeDaemon {
+    dir: TempDir,
+    engine: Arc<Mutex<Engine>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl FakeDaemon {
+    pub fn start() -> Self {
+        let dir = TempDir::new().unwrap();
+        let cli = FakeCli::new(&dir.path().join("cli"));
+        let listener = UnixListener::bind(dir.path().join("docker.sock")).unwrap();
+        fs::create_dir_all(dir.path().join("fs")).unwrap();
+        let engine = Arc::new(Mutex::new(Engine {
+            cli,
+            fs_root: dir.path().join("fs"),
+            ..Default::default()
+        }));
+        let stopped = Arc::new(AtomicBool::new(false));
+        let (e, s) = (engine.clone(), stopped.clone());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if s.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Ok(stream) = stream else { break };
+                let e = e.clone();
+                thread::spawn(move || serve(stream, &e));
+            }
+        });
+
+        FakeDaemon {
+            dir,
+            engine,
+            stopped,
+        }
+    }
+
+    pub fn endpoint(&self) -> Endpoint {
+        Endpoint {
+            socket: self.dir.path().join("docker.sock"),
+            cli: self.engine.lock().unwrap().cli.path(),
+            state_dir: self.dir.path().join("state"),
+        }
+    }
+
+    /// make the clients and state stores created on this thread use this daemon until it is
+    /// dropped
+    pub fn install(&self) {
+        INSTALLED.with(|i| *i.borrow_mut() = Some(self.endpoint()));
+    }
+
+    pub fn add_image(&self, tag: &str, labels: &[(&str, &str)]) -> String {
+        self.engine().add_image(Some(tag), to_map(labels))
+    }
+
+    pub fn add_container(
+        &self,
+        name: &str,
+        image: &str,
+        labels: &[(&str, &str)],
+        running: bool,
+    ) -> String {
+        self.engine()
+            .add_container(name, image, to_map(labels), vec![], running)
+    }
+
+    pub fn containers(&self) -> Vec<ContainerSummary> {
+        let engine = self.engine();
+        engine.containers.iter().map(|c| c.summary(false)).collect()
+    }
+
+    pub fn images(&self) -> Vec<ImageSummary> {
+        self.engine().images.iter().map(|i| i.summary()).collect()
+    }
+
+    /// arguments of the fake docker cli calls, in order
+    pub fn cli_calls(&self) -> Vec<Vec<String>> {
+        self.engine().cli_calls.clone()
+    }
+
+    /// the cli calls as shell quoted command lines, one per line
+    pub fn cli_transcript(&self) -> String {
+        self.cli_calls()
+            .iter()
+            .map(|call| {
+                let args: Vec<String> = call.iter().map(|a| quote(a)).collect();
+                format!("{}\n", args.join(" "))
+            })
+            .collect()
+    }
+
+    /// make the following cli calls print `output` and exit with `code`
+    pub fn script_cli(&self, code: i32, output: &str) {
+        self.engine().cli.script(code, output);
+    }
+
+    fn engine(&self) -> std::sync::MutexGuard<'_, Engine> {
+        let mut engine = self.engine.lock().unwrap();
+        engine.replay();
+        engine
+    }
+}
+
+impl Drop for FakeDaemon {
+    fn drop(&mut self) {
+        INSTALLED.with(|i| i.borrow_mut().take());
+        // wake up the accept loop so it sees it is stopped
+        self.stopped.store(true, Ordering::Relaxed);
+        let _ = UnixStream::connect(self.dir.path().join("docker.sock"));
+    }
+}
+
+/// shell script standing in for the docker cli, it records its arguments and answers with
+/// the scripted output and exit code, 0 by default
+#[derive(Debug, Clone, Default)]
+pub struct FakeCli {
+    dir: PathBuf,
+}
+
+impl FakeCli {
+    pub fn new(dir: &Path) -> Self {
+        fs::create_dir_all(dir).unwrap();
+        let script = format!(
+            r#"#!/bin/sh
+dir='{}'
+printf '%s\0' "$#" "$@" >> "$dir/calls"
+if [ -f "$dir/output" ]; then cat "$dir/output"; fi
+exit "$(cat "$dir/exit" 2>/dev/null || echo 0)"
+"#,
+            dir.display()
+        );
+        let cli = FakeCli {
+            dir: dir.to_path_buf(),
+        };
+        fs::write(cli.path(), script).unwrap();
+        fs::set_permissions(cli.path(), fs::Permissions::from_mode(0o755)).unwrap();
+        cli
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.join("docker")
+    }
+
+    /// make the following calls print `output` and exit with `code`
+    pub fn script(&self, code: i32, output: &str) {
+        fs::write(self.dir.join("exit"), code.to_string()).unwrap();
+        fs::write(self.dir.join("output"), output).unwrap();
+    }
+
+    /// arguments of the calls so far, in order
+    pub fn calls(&self) -> Vec<Vec<String>> {
+        let content = fs::read(self.dir.join("calls")).unwrap_or_default();
+        let mut fields = content
+            .split(|b| *b == 0)
+            .map(|f| String::from_utf8_lossy(f).to_string());
+        let mut calls = vec![];
+        while let Some(count) = fields.next().and_then(|c| c.parse::<usize>().ok()) {
+            calls.push(fields.by_ref().take(count).collect());
+        }
+        calls
+    }
+}
+
+#[derive(Default)]
+struct Engine {
+    containers: Vec<FakeContainer>,
+    images: Vec<FakeImage>,
+    /// exec id to container id and command
+    execs: HashMap<String, (String, Vec<String>)>,
+    cli: FakeCli,
+    /// cli calls already applied
+    cli_calls: Vec<Vec<String>>,
+    /// container filesystems, one directory per container id
+    fs_root: PathBuf,
+    next_id: u64,
+}
+
+struct FakeContainer {
+    id: String,
+    name: String,
+    image: String,
+    image_id: String,
+    labels: HashMap<String, String>,
+    cmd: Vec<String>,
+    running: bool,
+    created: i64,
+}
+
+impl FakeContainer {
+    fn summary(&self, size: bool) -> ContainerSummary {
+        ContainerSummary {
+            id: Some(self.id.clone()),
+            names: Some(vec![format!("/{}", self.name)]),
+            image: Some(self.image.clone()),
+            image_id: Some(self.image_id.clone()),
+            command: Some(self.cmd.join(" ")),
+            created: Some(self.created),
+            labels: Some(self.labels.clone()),
+            state: Some(if self.running { "running" } else { "exited" }.to_string()),
+            size_rw: size.then_some(0),
+            size_root_fs: size.then_some(0),
+            ..Default::default()
+        }
+    }
+}
+
+struct FakeImage {
+    id: String,
+    tags: Vec<String>,
+    labels: HashMap<String, String>,
+    created: i64,
+}
+
+impl FakeImage {
+    fn summary(&self) -> ImageSummary {
+        ImageSummary {
+            id: self.id.clone(),
+            repo_tags: self.tags.clone(),
+            created: self.created,
+            labels: self.labels.clone(),
+            containers: -1,
+            ..Default::default()
+        }
+    }
+}
+
+impl Engine {
+    fn new_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("{:064x}", self.next_id)
+    }
+
+    fn now() -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+
+    /// add an image, the tag moves from the image carrying it like it does with docker
+    fn add_image(&mut self, tag: Option<&str>, labels: HashMap<String, String>) -> String {
+        let id = format!("sha256:{}", self.new_id());
+        let tags = match tag {
+            Some(tag) => {
+                let tag = normalize_tag(tag);
+                for image in &mut self.images {
+                    image.tags.retain(|t| t != &tag);
+                }
+                vec![tag]
+            }
+            None => vec![],
+        };
+        self.images.push(FakeImage {
+            id: id.clone(),
+            tags,
+            labels,
+            created: Engine::now(),
+        });
+        id
+    }
+
+    /// create a container, pulling its image if it is unknown
+    fn add_container(
+        &mut self,
+        name: &str,
+        image: &str,
+        labels: HashMap<String, String>,
+        cmd: Vec<String>,
+        running: bool,
+    ) -> String {
+        let image_id = match self.find_image(image) {
+            Some(i) => self.images[i].id.clone(),
+            None => self.add_image(Some(image), HashMap::new()),
+        };
+        let id = self.new_id();
+        let name = match name {
+            "" => format!("fake_{}", &id[56..]),
+            name => name.to_string(),
+        };
+        self.containers.push(FakeContainer {
+            id: id.clone(),
+            name,
+            image: image.to_string(),
+            image_id,
+            labels,
+            cmd,
+            running,
+            created: Engine::now(),
+        });
+        id
+    }
+
+    fn find_container(&self, name: &str) -> Option<usize> {
+        let name = name.trim_start_matches('/');
+        self.containers
+            .iter()
+            .position(|c| c.name == name || c.id == name)
+            .or_else(|| {
+                self.containers
+                    .iter()
+                    .position(|c| name.len() >= 4 && c.id.starts_with(name))
+            })
+    }
+
+    fn find_image(&self, name: &str) -> Option<usize> {
+        let tag = normalize_tag(name);
+        self.images
+            .iter()
+            .position(|i| i.id == name || i.tags.contains(&tag))
+            .or_else(|| {
+                let prefix = format!("sha256:{}", name.trim_start_matches("sha256:"));
+                self.images
+                    .iter()
+                    .position(|i| name.len() >= 4 && i.id.starts_with(&prefix))
+            })
+    }
+
+    /// apply the fake cli calls made since the last request
+    fn replay(&mut self) {
+        let calls = self.cli.calls();
+        for call in calls.into_iter().skip(self.cli_calls.len()) {
+            self.run_cli(&call);
+            self.cli_calls.push(call);
+        }
+    }
+
+    fn run_cli(&mut self, args: &[String]) {
+        let Some((cmd, args)) = args.split_first() else {
+            return;
+        };
+        let (flags, positional) = parse_flags(cmd, args);
+        let labels = flags
+            .iter()
+            .filter(|(f, _)| f == "--label")
+            .filter_map(|(_, v)| v.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let flag = |name: &str| flags.iter().find(|(f, _)| f == name).map(|(_, v)| v);
+        match cmd.as_str() {
+            "build" => {
+                self.add_image(flag("-t").map(|t| t.as_str()), labels);
+            }
+            "run" => {
+                let Some((image, cmd)) = positional.split_first() else {
+                    return;
+                };
+                let detached = flag("-d").is_some();
+                // foreground runs are over once the cli returns
+                if !detached && flag("--rm").is_some() {
+                    return;
+                }
+                let name = flag("--name").map(|n| n.as_str()).unwrap_or_default();
+                self.add_container(name, image, labels, cmd.to_vec(), detached);
+            }
+            "start" | "stop" => {
+                for name in &positional {
+                    if let Some(i) = self.find_container(name) {
+                        self.containers[i].running = cmd == "start";
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// split the arguments of a docker cli call into flags with their values and positional
+/// arguments, everything after the first positional argument is positional
+fn parse_flags(cmd: &str, args: &[String]) -> (Vec<(String, String)>, Vec<String>) {
+    let with_value = |arg: &str| match arg {
+        // `-t` is the tag in build but allocates a tty in run and exec
+        "-t" => cmd == "build",
+        _ => [
+            "--name",
+            "--label",
+            "--mount",
+            "-e",
+            "-w",
+            "-f",
+            "--cpus",
+            "--memory",
+            "--pids-limit",
+        ]
+        .contains(&arg),
+    };
+    let mut flags = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            let mut positional = vec![arg.clone()];
+            positional.extend(args.cloned());
+            return (flags, positional);
+        }
+        let value = match with_value(arg) {
+            true => args.next().cloned().unwrap_or_default(),
+            false => String::new(),
+        };
+        flags.push((arg.clone(), value));
+    }
+    (flags, vec![])
+}
+
+fn normalize_tag(name: &str) -> String {
+    let last = name.rsplit('/').next().unwrap_or(name);
+    if last.contains(':') || name.starts_with("sha256:") {
+        name.to_string()
+    } else {
+        format!("{}:latest", name)
+    }
+}
+
+fn to_map(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+struct Request {
+    method: String,
+    /// path without the api version prefix
+    path: String,
+    query: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn read(reader: &mut impl BufRead) -> Option<Request> {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let mut parts = line.split_whitespace();
+        let method = parts.next()?.to_string();
+        let target = parts.next()?.to_string();
+
+        let (mut length, mut chunked) = (0, false);
+        loop {
+            line.clear();
+            reader.read_line(&mut line).ok()?;
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((key, value)) = header.split_once(':') {
+                match key.to_ascii_lowercase().as_str() {
+                    "content-length" => length = value.trim().parse().ok()?,
+                    "transfer-encoding" => chunked = value.trim() == "chunked",
+                    _ => {}
+                }
+            }
+        }
+        let body = if chunked {
+            read_chunked(reader)?
+        } else {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).ok()?;
+            body
+        };
+
+        let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+        let path = match path.strip_prefix("/v") {
+            Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => {
+                &rest[rest.find('/').unwrap_or(rest.len())..]
+            }
+            _ => path,
+        };
+        let query = query
+            .split('&')
+            .filter_map(|p| p.split_once('='))
+            .map(|(k, v)| (decode(k), decode(v)))
+            .collect();
+        Some(Request {
+            method,
+            path: decode(path),
+            query,
+            body,
+        })
+    }
+
+    /// label filters of the `filters` parameter
+    fn label_filters(&self) -> Vec<String> {
+        self.query
+            .get("filters")
+            .and_then(|f| serde_json::from_str::<HashMap<String, Vec<String>>>(f).ok())
+            .and_then(|mut f| f.remove("label"))
+            .unwrap_or_default()
+    }
+}
+
+fn read_chunked(reader: &mut impl BufRead) -> Option<Vec<u8>> {
+    let mut body = vec![];
+    let mut line = String::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).ok()?;
+        let size = line.trim_end().split(';').next()?;
+        let size = usize::from_str_radix(size, 16).ok()?;
+        if size == 0 {
+            // trailers up to the empty line
+            while reader.read_line(&mut line).ok()? > 0 && line.trim_end() != "" {
+                line.clear();
+            }
+            return Some(body);
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..]).ok()?;
+        line.clear();
+        reader.read_line(&mut line).ok()?;
+    }
+}
+
+fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+                match u8::from_str_radix(hex, 16) {
+                    Ok(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    Err(_) => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn matches_labels(labels: &HashMap<String, String>, filters: &[String]) -> bool {
+    filters.iter().all(|f| match f.split_once('=') {
+        Some((key, value)) => labels.get(key).is_some_and(|v| v == value),
+        None => labels.contains_key(f),
+    })
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, value: Value) -> Response {
+        Response {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_vec(&value).unwrap(),
+        }
+    }
+
+    fn empty(status: u16) -> Response {
+        Response {
+            status,
+            content_type: "text/plain",
+            body: vec![],
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Response {
+        Response::json(status, json!({ "message": message }))
+    }
+
+    fn write(&self, out: &mut impl Write) -> std::io::Result<()> {
+        write!(
+            out,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+            self.status,
+            if self.status < 400 { "OK" } else { "Error" },
+            self.content_type,
+            self.body.len()
+        )?;
+        out.write_all(&self.body)?;
+        out.flush()
+    }
+}
+
+fn serve(stream: UnixStream, engine: &Mutex<Engine>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(stream);
+    while let Some(request) = Request::read(&mut reader) {
+        let response = {
+            let mut engine = engine.lock().unwrap();
+            engine.replay();
+            handle(&mut engine, &request)
+        };
+        if response.write(&mut writer).is_err() {
+            return;
+        }
+    }
+}
+
+fn handle(engine: &mut Engine, request: &Request) -> Response {
+    let segments: Vec<&str> = request.path.trim_start_matches('/').split('/').collect();
+    let container = |engine: &Engine, name: &str| {
+        engine
+            .find_container(name)
+            .ok_or_else(|| Response::error(404, &format!("No such container: {}", name)))
+    };
+    let result = match (request.method.as_str(), segments.as_slice()) {
+        ("GET" | "HEAD", ["_ping"]) => Ok(Response {
+            status: 200,
+            content_type: "text/plain",
+            body: b"OK".to_vec(),
+        }),
+        ("GET", ["version"]) => Ok(Response::json(
+            200,
+            json!({ "Version": "fake", "ApiVersion": "1.44", "Os": "linux" }),
+        )),
+        ("GET", ["info"]) => Ok(Response::json(
+            200,
+            json!({ "NCPU": FAKE_CPUS, "MemTotal": FAKE_MEMORY, "DockerRootDir": engine.fs_root }),
+        )),
+
+        ("GET", ["containers", "json"]) => {
+            let filters = request.label_filters();
+            let all = request.query.get("all").is_some_and(|a| a == "true" || a == "1");
+            let size = request.query.get("size").is_some_and(|s| s == "true" || s == "1");
+            let containers: Vec<_> = engine
+                .containers
+                .iter()
+                .filter(|c| all || c.running)
+                .filter(|c| matches_labels(&c.labels, &filters))
+                .map(|c| c.summary(size))
+                .collect();
+            Ok(Response::json(200, json!(containers)))
+        }
+        ("POST", ["containers", "create"]) => {
+            let name = request.query.get("name").cloned().unwrap_or_default();
+            if engine.find_container(&name).is_some() {
+                return Response::error(409, &format!("Conflict. The name {} is in use", name));
+            }
+            let config: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let labels = serde_json::from_value(config["Labels"].clone()).unwrap_or_default();
+            let cmd = serde_json::from_value(config["Cmd"].clone()).unwrap_or_default();
+            let image = config["Image"].as_str().unwrap_or_default();
+            let id = engine.add_container(&name, image, labels, cmd, false);
+            Ok(Response::json(201, json!({ "Id": id, "Warnings": [] })))
+        }
+        ("GET", ["containers", name, "json"]) => container(engine, name).map(|i| {
+            let c = &engine.containers[i];
+            Response::json(
+                200,
+                json!({
+                    "Id": c.id,
+                    "Name": format!("/{}", c.name),
+                    "Image": c.image_id,
+                    "State": { "Running": c.running, "Status": if c.running { "running" } else { "exited" } },
+                    "Config": { "Image": c.image, "Labels": c.labels, "Cmd": c.cmd },
+                }),
+            )
+        }),
+        ("POST", ["containers", name, action @ ("start" | "stop")]) => {
+            container(engine, name).map(|i| {
+                let running = *action == "start";
+                if engine.containers[i].running == running {
+                    return Response::empty(304);
+                }
+                engine.containers[i].running = running;
+                Response::empty(204)
+            })
+        }
+        ("DELETE", ["containers", name]) => container(engine, name).and_then(|i| {
+            let force = request.query.get("force").is_some_and(|f| f == "true" || f == "1");
+            if engine.containers[i].running && !force {
+                return Err(Response::error(409, "container is running, stop it first"));
+            }
+            let c = engine.containers.remove(i);
+            let _ = fs::remove_dir_all(engine.fs_root.join(&c.id));
+            Ok(Response::empty(204))
+        }),
+        ("GET", ["containers", name, "logs"]) => container(engine, name).map(|_| Response {
+            status: 200,
+            content_type: "application/vnd.docker.multiplexed-stream",
+            body: vec![],
+        }),
+        ("PUT", ["containers", name, "archive"]) => container(engine, name).and_then(|i| {
+            let dir = request.query.get("path").cloned().unwrap_or_default();
+            let dir = engine
+                .fs_root
+                .join(&engine.containers[i].id)
+                .join(dir.trim_start_matches('/'));
+            fs::create_dir_all(&dir)
+                .and_then(|_| tar::Archive::new(request.body.as_slice()).unpack(&dir))
+                .map(|_| Response::empty(200))
+                .map_err(|e| Response::error(500, &e.to_string()))
+        }),
+        ("GET" | "HEAD", ["containers", name, "archive"]) => container(engine, name).and_then(|i| {
+            let path = request.query.get("path").cloned().unwrap_or_default();
+            let local = engine
+                .fs_root
+                .join(&engine.containers[i].id)
+                .join(path.trim_start_matches('/'));
+            if fs::symlink_metadata(&local).is_err() {
+                return Err(Response::error(
+                    404,
+                    &format!("Could not find the file {} in container {}", path, name),
+                ));
+            }
+            let name = Path::new(&path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| ".".to_string());
+            archive::pack(&local, &name)
+                .map(|body| Response {
+                    status: 200,
+                    content_type: "application/x-tar",
+                    body,
+                })
+                .map_err(|e| Response::error(500, &e.to_string()))
+        }),
+        ("POST", ["containers", name, "exec"]) => container(engine, name).and_then(|i| {
+            if !engine.containers[i].running {
+                return Err(Response::error(409, "container is not running"));
+            }
+            let config: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let cmd = serde_json::from_value(config["Cmd"].clone()).unwrap_or_default();
+            let id = engine.new_id();
+            let container_id = engine.containers[i].id.clone();
+            engine.execs.insert(id.clone(), (container_id, cmd));
+            Ok(Response::json(201, json!({ "Id": id })))
+        }),
+        ("POST", ["exec", id, "start"]) => match engine.execs.contains_key(*id) {
+            true => Ok(Response {
+                status: 200,
+                content_type: "application/vnd.docker.multiplexed-stream",
+                body: vec![],
+            }),
+            false => Err(Response::error(404, &format!("No such exec instance: {}", id))),
+        },
+        ("GET", ["exec", id, "json"]) => match engine.execs.get(*id) {
+            Some((container_id, cmd)) => Ok(Response::json(
+                200,
+                json!({
+                    "ID": id,
+                    "ContainerID": container_id,
+                    "Running": false,
+                    "ExitCode": 0,
+                    "ProcessConfig": { "entrypoint": cmd.first(), "arguments": cmd.get(1..) },
+                }),
+            )),
+            None => Err(Response::error(404, &format!("No such exec instance: {}", id))),
+        },
+
+        ("GET", ["images", "json"]) => {
+            let filters = request.label_filters();
+            let images: Vec<_> = engine
+                .images
+                .iter()
+                .filter(|i| matches_labels(&i.labels, &filters))
+                .map(|i| i.summary())
+                .collect();
+            Ok(Response::json(200, json!(images)))
+        }
+        ("DELETE", ["images", name @ ..]) => {
+            let name = name.join("/");
+            match engine.find_image(&name) {
+                None => Err(Response::error(404, &format!("No such image: {}", name))),
+                Some(i) => {
+                    let id = engine.images[i].id.clone();
+                    if engine.containers.iter().any(|c| c.image_id == id) {
+                        Err(Response::error(
+                            409,
+                            &format!("unable to delete {}, image is being used", name),
+                        ))
+                    } else {
+                        let image = engine.images.remove(i);
+                        let mut items: Vec<Value> =
+                            image.tags.iter().map(|t| json!({ "Untagged": t })).collect();
+                        items.push(json!({ "Deleted": image.id }));
+                        Ok(Response::json(200, json!(items)))
+                    }
+                }
+            }
+        }
+        ("POST", ["build"]) => {
+            let labels = request
+                .query
+                .get("labels")
+                .and_then(|l| serde_json::from_str(l).ok())
+                .unwrap_or_default();
+            let tag = request.query.get("t").map(|t| t.as_str());
+            let id = engine.add_image(tag, labels);
+            let body = [
+                json!({ "stream": "Step 1/1 : FROM scratch\n" }),
+                json!({ "aux": { "ID": id } }),
+            ]
+            .iter()
+            .map(|v| format!("{}\n", v))
+            .collect::<String>();
+            Ok(Response {
+                status: 200,
+                content_type: "application/json",
+                body: body.into_bytes(),
+            })
+        }
+
+        _ => Err(Response::error(404, "page not found")),
+    };
+    result.unwrap_or_else(|e| e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        docker::client::{DockerClient, DockerClientImpl, ExecOptions, RunOptions, PROJECT_KEY},
+        error::DevspaceError,
+    };
+
+    fn client(daemon: &FakeDaemon) -> DockerClientImpl {
+        let endpoint = daemon.endpoint();
+        DockerClientImpl::with_socket(&endpoint.socket, endpoint.cli).unwrap()
+    }
+
+    #[test]
+    fn test_containers() {
+        let daemon = FakeDaemon::start();
+        daemon.add_container("aaa", "alpine", &[(PROJECT_KEY, "0123")], false);
+        daemon.add_container("bbb", "alpine", &[(PROJECT_KEY, "4567")], true);
+        let client = client(&daemon);
+
+        let containers = client.list_containers("0123").unwrap();
+        assert_eq!(containers.len(), 1);
+        assert_eq!(containers[0].names, Some(vec!["/aaa".to_string()]));
+        assert_eq!(containers[0].image.as_deref(), Some("alpine"));
+        assert_eq!(client.list_all_containers(true).unwrap().len(), 2);
+        assert_eq!(client.host_info().unwrap().cpus, FAKE_CPUS);
+
+        client.start_container("aaa").unwrap();
+        let containers = client.list_containers("0123").unwrap();
+        assert_eq!(containers[0].state.as_deref(), Some("running"));
+        client.stop_container("aaa").unwrap();
+        assert!(client.start_container("ccc").is_err());
+
+        client.remove_container("bbb").unwrap();
+        assert_eq!(daemon.containers().len(), 1);
+        assert!(client.remove_image("alpine").is_err(), "image is in use");
+    }
+
+    #[test]
+    fn test_images() {
+        let daemon = FakeDaemon::start();
+        daemon.add_image("aaa:latest", &[(PROJECT_KEY, "0123")]);
+        daemon.add_image("aaa:latest", &[(PROJECT_KEY, "0123")]);
+        let client = client(&daemon);
+
+        let images = client.list_images("0123").unwrap();
+        assert_eq!(images.len(), 2);
+        assert!(
+            images[0].repo_tags.is_empty(),
+            "the tag moved to the new image"
+        );
+        assert_eq!(images[1].repo_tags, ["aaa:latest"]);
+
+        client.remove_image(&images[0].id).unwrap();
+        assert_eq!(client.list_all_images().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_archive() {
+        let daemon = FakeDaemon::start();
+        daemon.add_container("aaa", "alpine", &[], true);
+        let client = client(&daemon);
+
+        let tmpdir = TempDir::new().unwrap();
+        let src = tmpdir.path().join("notes.txt");
+        fs::write(&src, "hello").unwrap();
+        client
+            .upload("aaa", "/work", archive::pack(&src, "notes.txt").unwrap())
+            .unwrap();
+
+        assert!(client.is_dir("aaa", "/work").unwrap());
+        assert!(!client.is_dir("aaa", "/work/notes.txt").unwrap());
+        assert!(!client.is_dir("aaa", "/missing").unwrap());
+        let dst = tmpdir.path().join("copy.txt");
+        archive::unpack(&client.download("aaa", "/work/notes.txt").unwrap(), &dst).unwrap();
+        assert_eq!(fs::read_to_string(dst).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_cli() {
+        let daemon = FakeDaemon::start();
+        let client = client(&daemon);
+        client
+            .run(&RunOptions {
+                name: Some("aaa"),
+                deattach: true,
+                image: "alpine",
+                labels: HashMap::from([(PROJECT_KEY, "0123")]),
+                args: vec!["sleep", "infinity"],
+                ..Default::default()
+            })
+            .unwrap();
+        client
+            .exec(&ExecOptions {
+                container: "aaa",
+                args: vec!["echo", "it's a test"],
+                ..Default::default()
+            })
+            .unwrap();
+
+        let containers = client.list_containers("0123").unwrap();
+        assert_eq!(containers.len(), 1);
+        assert_eq!(containers[0].state.as_deref(), Some("running"));
+        assert_eq!(containers[0].command.as_deref(), Some("sleep infinity"));
+        assert_eq!(
+            daemon.cli_calls()[1],
+            ["exec", "-it", "aaa", "echo", "it's a test"]
+        );
+    }
+
+    #[test]
+    fn test_cli_script() {
+        let daemon = FakeDaemon::start();
+        daemon.add_container("aaa", "alpine", &[], true);
+        daemon.script_cli(3, "hello\n");
+        let client = client(&daemon);
+
+        let tmpdir = TempDir::new().unwrap();
+        let log = tmpdir.path().join("exec.log");
+        let err = client
+            .exec(&ExecOptions {
+                container: "aaa",
+                args: vec!["make"],
+                log: Some(&log),
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DevspaceError>(),
+            Some(DevspaceError::CommandFailed { code: Some(3), .. })
+        ));
+        assert_eq!(fs::read_to_string(log).unwrap(), "hello\n");
+    }
+}
@@ -0,0 +1,302 @@
+use super::{
+    client::dry_run,
+    process::quote,
+    state::{process_socket, socket_mount, CONTAINER_SOCKET_DIR},
+};
+use anyhow::{bail, Context, Result};
+use std::{
+    ffi::OsStr,
+    fs,
+    io::{Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+use tracing::{debug, info};
+
+/// settings of the host gitconfig copied into containers, names ending in a dot cover a
+/// whole section, credential helpers, includes and shell aliases stay on the host
+const SHARED: [&str; 14] = [
+    "user.name",
+    "user.email",
+    "init.defaultbranch",
+    "core.autocrlf",
+    "pull.rebase",
+    "pull.ff",
+    "push.default",
+    "push.autosetupremote",
+    "fetch.prune",
+    "rebase.autosquash",
+    "merge.conflictstyle",
+    "diff.algorithm",
+    "color.ui",
+    "alias.",
+];
+/// settings for signing with the key of the host, shared along with its gpg agent
+const SIGNING: [&str; 3] = ["user.signingkey", "commit.gpgsign", "tag.gpgsign"];
+/// name of the credential sockets, made unique per process by `process_socket`
+const CREDENTIAL_SOCKET: &str = "credential";
+/// variable naming the credential socket of the devspace process that runs a command
+const CREDENTIAL_SOCKET_ENV: &str = "DEVSPACE_CREDENTIAL_SOCK";
+
+/// what of the git config of the host to share with containers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GitConfig {
+    /// bridge `git credential` to the host
+    credentials: bool,
+    /// share the signing settings along with the gpg agent
+    signing: bool,
+}
+
+impl GitConfig {
+    pub fn new(credentials: bool, signing: bool) -> Self {
+        GitConfig {
+            credentials,
+            signing,
+        }
+    }
+
+    /// the shared settings of the global gitconfig, none if git or the config is missing
+    pub fn host_settings(&self) -> Vec<(String, String)> {
+        #[cfg(test)]
+        if super::fake::installed().is_some() {
+            return vec![];
+        }
+        let list = Command::new("git")
+            .args(["config", "--global", "--null", "--list"])
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| o.stdout)
+            .unwrap_or_default();
+        self.settings(&list)
+    }
+
+    /// the shared settings in the output of `git config --null --list`
+    fn settings(&self, list: &[u8]) -> Vec<(String, String)> {
+        let signing: &[&str] = if self.signing { &SIGNING } else { &[] };
+        String::from_utf8_lossy(list)
+            .split_terminator('\0')
+            .filter_map(|entry| entry.split_once('\n'))
+            // `!` aliases run host commands, which may be missing in the container
+            .filter(|(key, value)| !(key.starts_with("alias.") && value.starts_with('!')))
+            .filter(|(key, _)| {
+                SHARED
+                    .iter()
+                    .chain(signing)
+                    .any(|s| match s.strip_suffix('.') {
+                        Some(section) => key
+                            .strip_prefix(section)
+                            .is_some_and(|k| k.starts_with('.')),
+                        None => key == s,
+                    })
+            })
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// shell script writing `settings` to the gitconfig of the container user and trusting
+    /// `workspace`, doing nothing where git is not installed
+    pub fn script(&self, settings: &[(String, String)], workspace: &str) -> String {
+        let mut lines = vec!["command -v git >/dev/null || exit 0".to_string()];
+        let mut set = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|a| quote(a)).collect();
+            lines.push(format!("git config --global {}", args.join(" ")));
+        };
+        for (key, value) in settings {
+            set(&[key, value]);
+        }
+        // safe.directory is the one setting taking multiple values
+        set(&["--add", "safe.directory", workspace]);
+        if self.credentials {
+            let helper = format!(
+                "!f() {{ {{ echo \"$1\"; cat; }} | socat -t 30 - UNIX-CONNECT:\"${}\" 2>/dev/null; }}; f",
+                CREDENTIAL_SOCKET_ENV
+            );
+            set(&["credential.helper", &helper]);
+        }
+        lines.join("\n")
+    }
+
+    /// the credential socket of this process for the helper as `KEY=value`, None unless
+    /// bridging credentials
+    pub fn env(&self) -> Option<String> {
+        self.credentials.then(|| {
+            format!(
+                "{}={}/{}",
+                CREDENTIAL_SOCKET_ENV,
+                CONTAINER_SOCKET_DIR,
+                process_socket(CREDENTIAL_SOCKET)
+            )
+        })
+    }
+
+    /// bind mount of the host directory `socket_dir` holding the credential socket, None
+    /// unless bridging credentials
+    pub fn mount(&self, socket_dir: &Path) -> Option<String> {
+        self.credentials.then(|| socket_mount(socket_dir))
+    }
+
+    /// answer credential requests on the socket of this process in `socket_dir` until the
+    /// returned bridge is dropped, None unless bridging credentials
+    pub fn bridge(&self, socket_dir: &Path) -> Result<Option<Bridge>> {
+        if !self.credentials || dry_run() {
+            return Ok(None);
+        }
+        let socket = socket_dir.join(process_socket(CREDENTIAL_SOCKET));
+        Bridge::start(&socket, OsStr::new("git")).map(Some)
+    }
+}
+
+/// listener passing the requests of the credential helper in the container to
+/// `git credential` on the host
+///
+/// A request is the action git gave the helper on the first line, followed by the helper's
+/// input. The answer is the output of `git credential fill` for `get`, nothing otherwise.
+pub struct Bridge {
+    socket: PathBuf,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Bridge {
+    fn start(socket: &Path, git: &OsStr) -> Result<Self> {
+        let _ = fs::remove_file(socket);
+        let listener = UnixListener::bind(socket)
+            .with_context(|| format!("failed to listen on {:?}", socket))?;
+        info!("forwarding git credentials");
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let (s, git) = (stopped.clone(), git.to_owned());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if s.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Ok(stream) = stream else { break };
+                if let Err(e) = answer(stream, &git) {
+                    debug!("credential request failed: {:#}", e);
+                }
+            }
+        });
+        Ok(Bridge {
+            socket: socket.to_path_buf(),
+            stopped,
+        })
+    }
+}
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        // wake the listener so it sees it is stopped
+        let _ = UnixStream::connect(&self.socket);
+        let _ = fs::remove_file(&self.socket);
+    }
+}
+
+fn answer(mut stream: UnixStream, git: &OsStr) -> Result<()> {
+    let mut request = String::new();
+    stream.read_to_string(&mut request)?;
+    let (action, input) = request.split_once('\n').unwrap_or((&request, ""));
+    let operation = match action.trim() {
+        "get" => "fill",
+        "store" => "approve",
+        "erase" => "reject",
+        action => bail!("unknown credential action {:?}", action),
+    };
+    let mut child = Command::new(git)
+        .args(["credential", operation])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("failed to run {:?}", git))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if operation == "fill" && output.status.success() {
+        stream.write_all(&output.stdout)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::Shutdown, os::unix::fs::PermissionsExt};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_script() {
+        let list = b"user.name\nA B\0user.email\na@b.c\0credential.helper\nstore\0alias.co\ncheckout\0alias.sh\n!make\0aliases.x\ny\0";
+        let git = GitConfig::new(false, false);
+        assert_eq!(
+            git.script(&git.settings(list), "/workspaces/aaa"),
+            "command -v git >/dev/null || exit 0
+git config --global user.name 'A B'
+git config --global user.email a@b.c
+git config --global alias.co checkout
+git config --global --add safe.directory /workspaces/aaa"
+        );
+        assert_eq!(git.mount(Path::new("/state/aaa")), None);
+        assert_eq!(git.env(), None);
+
+        let git = GitConfig::new(false, true);
+        assert_eq!(
+            git.settings(b"user.signingkey\nABCD\0"),
+            [("user.signingkey".into(), "ABCD".into())]
+        );
+
+        let git = GitConfig::new(true, false);
+        assert!(git.script(&[], "/workspaces/aaa").ends_with(
+            r#"socat -t 30 - UNIX-CONNECT:"$DEVSPACE_CREDENTIAL_SOCK" 2>/dev/null; }; f'"#
+        ));
+        assert_eq!(
+            git.env().unwrap(),
+            format!(
+                "DEVSPACE_CREDENTIAL_SOCK=/tmp/devspace/host/credential-{}.sock",
+                std::process::id()
+            )
+        );
+        assert_eq!(
+            git.mount(Path::new("/state/aaa")).unwrap(),
+            "type=bind,source=/state/aaa,target=/tmp/devspace/host"
+        );
+    }
+
+    #[test]
+    fn test_bridge() {
+        let dir = TempDir::new().unwrap();
+        // stands in for git, answering with its arguments and input
+        let git = dir.path().join("git");
+        fs::write(&git, "#!/bin/sh\necho \"$@\"\ncat\n").unwrap();
+        fs::set_permissions(&git, fs::Permissions::from_mode(0o755)).unwrap();
+        let socket = dir.path().join(process_socket(CREDENTIAL_SOCKET));
+        let bridge = Bridge::start(&socket, git.as_os_str()).unwrap();
+
+        let request = |body: &str| {
+            let mut stream = UnixStream::connect(&socket).unwrap();
+            stream.write_all(body.as_bytes()).unwrap();
+            stream.shutdown(Shutdown::Write).unwrap();
+            let mut answer = String::new();
+            stream.read_to_string(&mut answer).unwrap();
+            answer
+        };
+        assert_eq!(
+            request("get\nprotocol=https\nhost=example.com\n\n"),
+            "credential fill\nprotocol=https\nhost=example.com\n\n"
+        );
+        assert_eq!(request("store\nusername=u\npassword=p\n\n"), "");
+
+        drop(bridge);
+        assert!(!socket.exists());
+    }
+}
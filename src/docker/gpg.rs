@@ -0,0 +1,124 @@
+use super::{archive, client::dry_run, process::quote};
+use anyhow::Result;
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// where the extra socket of the host agent appears in the container
+const CONTAINER_SOCKET: &str = "/tmp/devspace/gpg-agent.sock";
+/// directory and name of the uploaded public keyring
+const KEYRING_DIR: &str = "/tmp";
+const KEYRING: &str = "devspace-pubring.gpg";
+
+/// gpg agent of the host forwarded to the container
+///
+/// The extra socket of the agent is the one meant for remote use, it refuses to export or
+/// delete secret keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpgAgent {
+    /// extra socket of the host agent
+    socket: PathBuf,
+    /// public keys of the host in binary form
+    public_keys: Vec<u8>,
+}
+
+impl GpgAgent {
+    /// the running agent of the host, started if needed, None where gpg is not installed
+    ///
+    /// A dry run neither starts the agent nor exports keys, it only looks up the socket.
+    pub fn from_host() -> Option<Self> {
+        #[cfg(test)]
+        if super::fake::installed().is_some() {
+            return None;
+        }
+        if !dry_run() {
+            let _ = output("gpgconf", &["--launch", "gpg-agent"]);
+        }
+        let socket = output("gpgconf", &["--list-dirs", "agent-extra-socket"])?;
+        let socket = String::from_utf8(socket).ok()?.trim().into();
+        let public_keys = match dry_run() {
+            true => vec![],
+            false => output("gpg", &["--export"]).unwrap_or_default(),
+        };
+        Some(GpgAgent {
+            socket,
+            public_keys,
+        })
+    }
+
+    /// bind mount of the extra socket in `--mount` form
+    pub fn mount(&self) -> String {
+        format!(
+            "type=bind,source={},target={}",
+            self.socket.display(),
+            CONTAINER_SOCKET
+        )
+    }
+
+    /// directory to upload the keyring archive to and the archive
+    pub fn keyring(&self) -> Result<(&str, Vec<u8>)> {
+        Ok((KEYRING_DIR, archive::pack_data(&self.public_keys, KEYRING)?))
+    }
+
+    /// shell script linking the socket where gpg of the container user looks for its agent
+    /// and importing the uploaded keyring, doing nothing where gpg is not installed
+    pub fn script(&self) -> String {
+        let keyring = quote(&Path::new(KEYRING_DIR).join(KEYRING).display().to_string());
+        [
+            "command -v gpgconf >/dev/null || exit 0".to_string(),
+            "socket=$(gpgconf --list-dirs agent-socket)".to_string(),
+            r#"mkdir -p "$(dirname "$socket")" && chmod 700 "$(dirname "$socket")""#.to_string(),
+            format!(r#"ln -sf {} "$socket""#, quote(CONTAINER_SOCKET)),
+            format!("gpg --batch --quiet --import {}", keyring),
+            format!("rm -f {} 2>/dev/null || :", keyring),
+        ]
+        .join("\n")
+    }
+}
+
+/// stdout of a successful command, None if it could not run or failed
+fn output(cmd: impl AsRef<OsStr>, args: &[&str]) -> Option<Vec<u8>> {
+    Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| o.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gpg_agent() {
+        let agent = GpgAgent {
+            socket: "/run/user/1000/gnupg/S.gpg-agent.extra".into(),
+            public_keys: b"keys".to_vec(),
+        };
+        assert_eq!(
+            agent.mount(),
+            "type=bind,source=/run/user/1000/gnupg/S.gpg-agent.extra,target=/tmp/devspace/gpg-agent.sock"
+        );
+        assert_eq!(
+            agent.script(),
+            r#"command -v gpgconf >/dev/null || exit 0
+socket=$(gpgconf --list-dirs agent-socket)
+mkdir -p "$(dirname "$socket")" && chmod 700 "$(dirname "$socket")"
+ln -sf /tmp/devspace/gpg-agent.sock "$socket"
+gpg --batch --quiet --import /tmp/devspace-pubring.gpg
+rm -f /tmp/devspace-pubring.gpg 2>/dev/null || :"#
+        );
+
+        let (dir, keyring) = agent.keyring().unwrap();
+        assert_eq!(dir, "/tmp");
+        let mut entries = tar::Archive::new(keyring.as_slice());
+        let entry = entries.entries().unwrap().next().unwrap().unwrap();
+        assert_eq!(entry.path().unwrap(), Path::new("devspace-pubring.gpg"));
+        assert_eq!(entry.size(), 4);
+    }
+}
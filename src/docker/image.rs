@@ -1,29 +1,36 @@
-use crate::{docker::client::DockerClient, project::ImageSource};
-use anyhow::{anyhow, Result};
+use crate::{
+    docker::client::{DockerClient, NAME_KEY, PROJECT_KEY, ROOT_KEY},
+    error::DevspaceError,
+    project::{ImageSource, Project},
+};
+use anyhow::{anyhow, Context, Result};
 use bollard::models::ImageSummary;
+use std::{collections::HashMap, path::PathBuf};
+use tracing::info_span;
 
 pub trait Image {
     fn name(&self) -> &str;
     fn existing(&self) -> bool;
+    /// local image details, None for images not managed by devspace
+    fn summary(&self) -> Option<&ImageSummary>;
     fn build(&mut self, client: &dyn DockerClient) -> Result<()>;
 }
 
-pub fn new_image(
-    project_name: &str,
-    source: &ImageSource,
-    client: &dyn DockerClient,
-) -> Result<Box<dyn Image>> {
-    match source {
+pub fn new_image(project: &Project, client: &dyn DockerClient) -> Result<Box<dyn Image>> {
+    match &project.config.image_source {
         ImageSource::Image(name) => Ok(Box::new(ForeignImage {
             name: name.to_owned(),
         })),
         ImageSource::Dockerfile(dockerfile) => {
-            let images = client.list_images(project_name)?;
-            let name = format!("{}:latest", project_name);
-            let summary = images.iter().find(|i| i.repo_tags.contains(&name));
+            let images = client.list_images(&project.id)?;
+            let tag = project.image_tag();
+            let summary = images.iter().find(|i| i.repo_tags.contains(&tag));
 
             Ok(Box::new(DockrefileImage {
-                project_name: project_name.to_string(),
+                project_id: project.id.clone(),
+                project_name: project.name.clone(),
+                tag,
+                root: project.root.clone(),
                 dockerfile: dockerfile.to_string(),
                 summary: summary.cloned(),
             }))
@@ -44,34 +51,57 @@ impl Image for ForeignImage {
         true
     }
 
+    fn summary(&self) -> Option<&ImageSummary> {
+        None
+    }
+
     fn build(&mut self, _: &dyn DockerClient) -> Result<()> {
         Ok(())
     }
 }
 
 struct DockrefileImage {
+    project_id: String,
     project_name: String,
+    tag: String,
+    root: PathBuf,
     dockerfile: String,
     summary: Option<ImageSummary>,
 }
 
 impl Image for DockrefileImage {
     fn name(&self) -> &str {
-        &self.project_name
+        &self.tag
     }
 
     fn existing(&self) -> bool {
         self.summary.is_some()
     }
 
+    fn summary(&self) -> Option<&ImageSummary> {
+        self.summary.as_ref()
+    }
+
     fn build(&mut self, client: &dyn DockerClient) -> Result<()> {
-        client.build_image(&self.project_name, &self.dockerfile)?;
-        let summaries = client.list_images(&self.project_name)?;
-        let tag = format!("{}:latest", self.project_name);
-        self.summary = summaries.into_iter().find(|i| i.repo_tags.contains(&tag));
+        let _span = info_span!("build", image = self.tag).entered();
+        let root = self.root.display().to_string();
+        let labels = HashMap::from([
+            (PROJECT_KEY, self.project_id.as_str()),
+            (NAME_KEY, self.project_name.as_str()),
+            (ROOT_KEY, root.as_str()),
+        ]);
+        client.build_image(&self.tag, &self.dockerfile, &self.root, labels)?;
+        let summaries = client.list_images(&self.project_id)?;
+        self.summary = summaries
+            .into_iter()
+            .find(|i| i.repo_tags.contains(&self.tag));
 
         if self.summary.is_none() {
-            return Err(anyhow!("Image not found after build"));
+            return Err(anyhow!("image not found after build")).context(
+                DevspaceError::ImageBuildFailed {
+                    image: self.tag.clone(),
+                },
+            );
         }
 
         Ok(())
@@ -85,6 +115,7 @@ mod tests {
         docker::client::tests::MockDockerClient,
         project::{tests::TmpProjectDir, Project},
     };
+    use mockall::predicate::eq;
 
     #[test]
     fn test_foreign_image() {
@@ -97,8 +128,7 @@ mod tests {
         let project = Project::try_from(&tmp_project_dir.root).unwrap();
         let client = MockDockerClient::new();
 
-        let image: Box<dyn Image> =
-            new_image(&project.name, &project.config.image_source, &client).unwrap();
+        let image: Box<dyn Image> = new_image(&project, &client).unwrap();
         assert!(image.existing());
         assert_eq!(image.name(), "testimage:latest");
     }
@@ -116,10 +146,10 @@ mod tests {
         let mut mock_client = MockDockerClient::new();
         mock_client.expect_list_images().returning(|_| Ok(vec![]));
 
-        let image: Box<dyn Image> =
-            new_image(&project.name, &project.config.image_source, &mock_client).unwrap();
+        let image: Box<dyn Image> = new_image(&project, &mock_client).unwrap();
         assert!(!image.existing());
-        assert_eq!(image.name(), "aaa");
+        assert_eq!(image.name(), format!("{}:latest", project.image_name));
+        assert!(image.name().starts_with("aaa-"));
     }
 
     #[test]
@@ -132,17 +162,65 @@ mod tests {
         let tmp_project_dir = TmpProjectDir::new("yyyy").devcontainer_json(json);
         let project = Project::try_from(&tmp_project_dir.root).unwrap();
 
+        let tag = format!("{}:latest", project.image_name);
+        let mut mock_client = MockDockerClient::new();
+        let t = tag.clone();
+        mock_client
+            .expect_list_images()
+            .with(eq(project.id.clone()))
+            .returning(move |_| {
+                Ok(vec![ImageSummary {
+                    repo_tags: vec![t.clone()],
+                    ..Default::default()
+                }])
+            });
+
+        let image: Box<dyn Image> = new_image(&project, &mock_client).unwrap();
+        assert!(image.existing());
+        assert_eq!(image.name(), tag);
+    }
+
+    #[test]
+    fn test_dockerfile_image_build() {
+        let json = r#"{ "name": "ccc", "dockerFile": "Dockerfile" }"#;
+        let tmp_project_dir = TmpProjectDir::new("zzzz").devcontainer_json(json);
+        let project = Project::try_from(&tmp_project_dir.root).unwrap();
+        let tag = format!("{}:latest", project.image_name);
+
         let mut mock_client = MockDockerClient::new();
-        mock_client.expect_list_images().returning(|_| {
-            Ok(vec![ImageSummary {
-                repo_tags: vec!["bbb:latest".to_string()],
-                ..Default::default()
-            }])
-        });
-
-        let image: Box<dyn Image> =
-            new_image(&project.name, &project.config.image_source, &mock_client).unwrap();
+        let mut seq = mockall::Sequence::new();
+        mock_client
+            .expect_list_images()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(vec![]));
+        let (id, root, t) = (project.id.clone(), project.root.clone(), tag.clone());
+        mock_client
+            .expect_build_image()
+            .withf(move |tag, _, context, labels| {
+                tag == t
+                    && context == root
+                    && labels.get(PROJECT_KEY) == Some(&id.as_str())
+                    && labels.get(NAME_KEY) == Some(&"ccc")
+            })
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _, _, _| Ok(()));
+        let t = tag.clone();
+        mock_client
+            .expect_list_images()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(move |_| {
+                Ok(vec![ImageSummary {
+                    repo_tags: vec![t.clone()],
+                    ..Default::default()
+                }])
+            });
+
+        let mut image = new_image(&project, &mock_client).unwrap();
+        assert!(!image.existing());
+        image.build(&mock_client).unwrap();
         assert!(image.existing());
-        assert_eq!(image.name(), "bbb");
     }
 }
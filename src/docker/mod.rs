@@ -1,8 +1,25 @@
+mod agent;
+mod archive;
 mod client;
 mod container;
+mod dry_run;
+mod ephemeral;
+#[cfg(test)]
+pub mod fake;
+mod git;
+mod gpg;
 mod image;
 mod process;
+mod prune;
+mod reap;
+mod resources;
 mod state;
 
-pub use container::Container;
+pub use client::{find_docker_cli, set_dry_run, DockerSource, LogOptions};
+#[cfg(test)]
+pub use client::{PROJECT_KEY, ROOT_KEY};
+pub use container::{all_containers, Container};
+pub use ephemeral::Ephemeral;
 pub use image::Image;
+pub use prune::Pruner;
+pub use reap::Reaper;
@@ -1,9 +1,16 @@
+use crate::error::DevspaceError;
 use anyhow::{Context, Result};
 use std::{
     ffi::OsStr,
     fmt::Debug,
-    process::{Command, Stdio},
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    path::Path,
+    process::{Command, ExitStatus, Stdio},
+    sync::Mutex,
+    thread,
 };
+use tracing::debug;
 
 pub fn pipe_cmd<C, T, S>(cmd: C, args: T) -> Result<()>
 where
@@ -11,21 +18,186 @@ where
     T: IntoIterator<Item = S> + Debug,
     S: AsRef<OsStr>,
 {
+    let args: Vec<S> = args.into_iter().collect();
+    debug!("running {}", command_line(&cmd, &args));
     let mut child = Command::new(&cmd)
-        .args(args)
+        .args(&args)
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
         .stdin(Stdio::inherit())
         .spawn()?;
 
-    match child.wait() {
+    check_status(&cmd, &args, child.wait())
+}
+
+/// like `pipe_cmd`, but stdout and stderr are also appended to the file at `log`
+pub fn tee_cmd<C, T, S>(cmd: C, args: T, log: &Path) -> Result<()>
+where
+    C: AsRef<OsStr>,
+    T: IntoIterator<Item = S> + Debug,
+    S: AsRef<OsStr>,
+{
+    let args: Vec<S> = args.into_iter().collect();
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log)
+        .with_context(|| format!("failed to open log file {:?}", log))?;
+    let log = Mutex::new(log);
+    debug!("running {}", command_line(&cmd, &args));
+    let mut child = Command::new(&cmd)
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .stdin(Stdio::inherit())
+        .spawn()?;
+
+    let (out, err) = (child.stdout.take(), child.stderr.take());
+    let status = thread::scope(|s| {
+        let log = &log;
+        let out = s.spawn(move || out.map(|out| tee(out, io::stdout(), log)));
+        let err = s.spawn(move || err.map(|err| tee(err, io::stderr(), log)));
+        // the output is drained before waiting, the command may block on a full pipe otherwise
+        let copied = [out.join(), err.join()];
+        let status = child.wait();
+        for copied in copied {
+            if let Ok(Some(Err(e))) = copied {
+                return Err(e);
+            }
+        }
+        Ok(status)
+    })
+    .context("failed to copy command output")?;
+
+    check_status(&cmd, &args, status)
+}
+
+/// copy `from` to `to` and the log until it ends, after a failed write the rest is still read so
+/// the writing command does not block, the first error is returned at the end
+fn tee(mut from: impl Read, mut to: impl Write, log: &Mutex<File>) -> io::Result<()> {
+    let mut buffer = [0; 8192];
+    let mut result = Ok(());
+    loop {
+        let n = match from.read(&mut buffer) {
+            Ok(0) => return result,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return result.and(Err(e)),
+        };
+        if result.is_ok() {
+            result = copy(&buffer[..n], &mut to, log);
+        }
+    }
+}
+
+fn copy(data: &[u8], to: &mut impl Write, log: &Mutex<File>) -> io::Result<()> {
+    to.write_all(data)?;
+    to.flush()?;
+    log.lock()
+        .map_err(|_| io::Error::other("log file lock poisoned"))?
+        .write_all(data)
+}
+
+fn check_status<C: AsRef<OsStr>, S: AsRef<OsStr>>(
+    cmd: C,
+    args: &[S],
+    status: io::Result<ExitStatus>,
+) -> Result<()> {
+    match status {
         Ok(status) => {
             if status.success() {
                 Ok(())
             } else {
-                Err(anyhow::anyhow!("Command failed with status: {}", status))
+                Err(DevspaceError::CommandFailed {
+                    command: command_line(&cmd, args),
+                    code: status.code(),
+                }
+                .into())
             }
         }
         Err(e) => Err(e).with_context(|| anyhow::anyhow!("Failed to execute {:?}", cmd.as_ref())),
     }
 }
+
+fn command_line<C: AsRef<OsStr>, S: AsRef<OsStr>>(cmd: C, args: &[S]) -> String {
+    let mut line = vec![cmd.as_ref().to_string_lossy()];
+    line.extend(args.iter().map(|a| a.as_ref().to_string_lossy()));
+    line.join(" ")
+}
+
+/// quote `arg` for a POSIX shell, if needed
+pub fn quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(
+            quote("type=bind,source=/a,target=/b"),
+            "type=bind,source=/a,target=/b"
+        );
+        assert_eq!(quote("echo hello"), "'echo hello'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote(""), "''");
+    }
+
+    #[test]
+    fn test_pipe_cmd_exit_code() {
+        pipe_cmd("sh", ["-c", "exit 0"]).unwrap();
+
+        let err = pipe_cmd("sh", ["-c", "exit 3"]).unwrap_err();
+        match err.downcast_ref::<DevspaceError>() {
+            Some(DevspaceError::CommandFailed { command, code }) => {
+                assert_eq!(command, "sh -c exit 3");
+                assert_eq!(*code, Some(3));
+            }
+            _ => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_tee_cmd() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let log = tmpdir.path().join("lifecycle.log");
+        std::fs::write(&log, "before\n").unwrap();
+
+        let err = tee_cmd("sh", ["-c", "echo out; echo err >&2; exit 2"], &log).unwrap_err();
+        match err.downcast_ref::<DevspaceError>() {
+            Some(DevspaceError::CommandFailed { code, .. }) => assert_eq!(*code, Some(2)),
+            _ => panic!("unexpected error {:?}", err),
+        }
+        let content = std::fs::read_to_string(&log).unwrap();
+        assert!(content.starts_with("before\n"));
+        assert!(content.contains("out\n"));
+        assert!(content.contains("err\n"));
+    }
+
+    #[test]
+    fn test_tee_drains_after_write_error() {
+        struct Broken;
+        impl Write for Broken {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let log = Mutex::new(File::create(tmpdir.path().join("log")).unwrap());
+        let mut data = io::Cursor::new(vec![b'x'; 100_000]);
+        let err = tee(&mut data, Broken, &log).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(data.position(), 100_000);
+    }
+}
@@ -0,0 +1,289 @@
+use super::client::{new_client, DockerClient, NAME_KEY, ROOT_KEY};
+use anyhow::Result;
+use bollard::models::{ContainerSummary, ImageSummary};
+use serde::Serialize;
+use std::{collections::HashSet, fmt, path::Path};
+
+const UNTAGGED: &str = "<none>:<none>";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Container,
+    Image,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Container => write!(f, "container"),
+            Kind::Image => write!(f, "image"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    /// the project root recorded at creation time no longer exists
+    RootMissing(String),
+    /// the image lost its tag, usually because the project image was rebuilt
+    Untagged,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::RootMissing(root) => write!(f, "project root {} is gone", root),
+            Reason::Untagged => write!(f, "untagged"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Prunable {
+    pub kind: Kind,
+    pub id: String,
+    pub name: String,
+    pub reason: Reason,
+    /// reclaimable bytes
+    pub size: i64,
+    pub created: i64,
+}
+
+pub struct Pruner {
+    client: Box<dyn DockerClient>,
+}
+
+impl Pruner {
+    pub fn new() -> Result<Self> {
+        Ok(Pruner::with_client(new_client()?))
+    }
+
+    fn with_client(client: Box<dyn DockerClient>) -> Self {
+        Pruner { client }
+    }
+
+    /// find devspace resources that can be removed, optionally only the ones created
+    /// before the given unix timestamp
+    pub fn find(&self, created_before: Option<i64>) -> Result<Vec<Prunable>> {
+        let old_enough = |created: i64| created_before.is_none_or(|t| created < t);
+
+        let containers = self.client.list_all_containers(true)?;
+        let mut prunables: Vec<Prunable> = containers
+            .iter()
+            .filter_map(container_prunable)
+            .filter(|p| old_enough(p.created))
+            .collect();
+
+        // images still used by a container we keep can not be removed
+        let removed: HashSet<&str> = prunables.iter().map(|p| p.id.as_str()).collect();
+        let in_use: HashSet<&str> = containers
+            .iter()
+            .filter(|c| !c.id.as_deref().is_some_and(|id| removed.contains(id)))
+            .filter_map(|c| c.image_id.as_deref())
+            .collect();
+
+        let images: Vec<Prunable> = self
+            .client
+            .list_all_images()?
+            .iter()
+            .filter(|i| !in_use.contains(i.id.as_str()))
+            .filter_map(image_prunable)
+            .filter(|p| old_enough(p.created))
+            .collect();
+
+        prunables.extend(images);
+        Ok(prunables)
+    }
+
+    /// remove the given resources, containers first so their images can be removed afterwards
+    pub fn remove(&self, prunables: &[Prunable]) -> Result<()> {
+        for p in prunables.iter().filter(|p| p.kind == Kind::Container) {
+            self.client.remove_container(&p.id)?;
+        }
+        for p in prunables.iter().filter(|p| p.kind == Kind::Image) {
+            self.client.remove_image(&p.id)?;
+        }
+        Ok(())
+    }
+}
+
+fn root_missing(root: Option<&String>) -> Option<Reason> {
+    root.filter(|root| !Path::new(root).exists())
+        .map(|root| Reason::RootMissing(root.to_string()))
+}
+
+fn container_prunable(summary: &ContainerSummary) -> Option<Prunable> {
+    let labels = summary.labels.as_ref()?;
+    let reason = root_missing(labels.get(ROOT_KEY))?;
+    let name = summary
+        .names
+        .as_ref()
+        .and_then(|names| names.first())
+        .map(|n| n.trim_start_matches('/').to_string())
+        .or_else(|| labels.get(NAME_KEY).cloned())
+        .unwrap_or_default();
+
+    Some(Prunable {
+        kind: Kind::Container,
+        id: summary.id.clone()?,
+        name,
+        reason,
+        size: summary.size_rw.unwrap_or_default(),
+        created: summary.created.unwrap_or_default(),
+    })
+}
+
+fn image_prunable(summary: &ImageSummary) -> Option<Prunable> {
+    let tags: Vec<&String> = summary
+        .repo_tags
+        .iter()
+        .filter(|t| t.as_str() != UNTAGGED)
+        .collect();
+    let reason = if tags.is_empty() {
+        Reason::Untagged
+    } else {
+        root_missing(summary.labels.get(ROOT_KEY))?
+    };
+    let name = match tags.first() {
+        Some(tag) => tag.to_string(),
+        None => summary
+            .labels
+            .get(NAME_KEY)
+            .map(|p| format!("{} (untagged)", p))
+            .unwrap_or_else(|| UNTAGGED.to_string()),
+    };
+
+    Some(Prunable {
+        kind: Kind::Image,
+        id: summary.id.clone(),
+        name,
+        reason,
+        size: summary.size,
+        created: summary.created,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker::client::{tests::MockDockerClient, PROJECT_KEY};
+    use mockall::predicate::eq;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn container(id: &str, image_id: &str, root: &str, created: i64) -> ContainerSummary {
+        ContainerSummary {
+            id: Some(id.to_string()),
+            names: Some(vec![format!("/{}", id)]),
+            image_id: Some(image_id.to_string()),
+            labels: Some(HashMap::from([
+                (PROJECT_KEY.to_string(), id.to_string()),
+                (NAME_KEY.to_string(), id.to_string()),
+                (ROOT_KEY.to_string(), root.to_string()),
+            ])),
+            size_rw: Some(10),
+            created: Some(created),
+            ..Default::default()
+        }
+    }
+
+    fn image(id: &str, tags: &[&str], root: &str, created: i64) -> ImageSummary {
+        ImageSummary {
+            id: id.to_string(),
+            repo_tags: tags.iter().map(|t| t.to_string()).collect(),
+            labels: HashMap::from([
+                (PROJECT_KEY.to_string(), "0123456789ab".to_string()),
+                (NAME_KEY.to_string(), "aaa".to_string()),
+                (ROOT_KEY.to_string(), root.to_string()),
+            ]),
+            size: 100,
+            created,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_find() {
+        let tmpdir = TempDir::new().unwrap();
+        let existing = tmpdir.path().display().to_string();
+        let gone = tmpdir.path().join("gone").display().to_string();
+
+        let mut client = MockDockerClient::new();
+        let (e, g) = (existing.clone(), gone.clone());
+        client.expect_list_all_containers().returning(move |_| {
+            Ok(vec![
+                container("kept", "sha:kept", &e, 1),
+                container("orphan", "sha:orphan", &g, 1),
+            ])
+        });
+        let (e, g) = (existing.clone(), gone.clone());
+        client.expect_list_all_images().returning(move || {
+            Ok(vec![
+                image("sha:kept", &["kept:latest"], &g, 1),
+                image("sha:orphan", &["orphan:latest"], &g, 1),
+                image("sha:current", &["aaa:latest"], &e, 1),
+                image("sha:dangling", &[UNTAGGED], &e, 1),
+            ])
+        });
+
+        let prunables = Pruner::with_client(Box::new(client)).find(None).unwrap();
+        let ids: Vec<_> = prunables.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["orphan", "sha:orphan", "sha:dangling"]);
+        assert_eq!(prunables[0].name, "orphan");
+        assert_eq!(prunables[0].reason, Reason::RootMissing(gone));
+        assert_eq!(prunables[2].reason, Reason::Untagged);
+        assert_eq!(prunables[2].name, "aaa (untagged)");
+    }
+
+    #[test]
+    fn test_find_older_than() {
+        let mut client = MockDockerClient::new();
+        client
+            .expect_list_all_containers()
+            .returning(|_| Ok(vec![]));
+        client.expect_list_all_images().returning(|| {
+            Ok(vec![
+                image("sha:old", &[], "/", 10),
+                image("sha:new", &[], "/", 20),
+            ])
+        });
+
+        let prunables = Pruner::with_client(Box::new(client))
+            .find(Some(15))
+            .unwrap();
+        assert_eq!(prunables.len(), 1);
+        assert_eq!(prunables[0].id, "sha:old");
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut client = MockDockerClient::new();
+        let mut seq = mockall::Sequence::new();
+        client
+            .expect_remove_container()
+            .with(eq("c1"))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(()));
+        client
+            .expect_remove_image()
+            .with(eq("i1"))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(()));
+
+        let prunable = |kind, id: &str| Prunable {
+            kind,
+            id: id.to_string(),
+            name: id.to_string(),
+            reason: Reason::Untagged,
+            size: 0,
+            created: 0,
+        };
+        Pruner::with_client(Box::new(client))
+            .remove(&[prunable(Kind::Image, "i1"), prunable(Kind::Container, "c1")])
+            .unwrap();
+    }
+}
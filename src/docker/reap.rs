@@ -0,0 +1,136 @@
+use super::{
+    client::{dry_run, new_client, DockerClient, PROJECT_KEY},
+    state::{alive, FileStore, MemoryStore, StateStore},
+};
+use anyhow::Result;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// stops containers nobody works in
+pub struct Reaper {
+    client: Box<dyn DockerClient>,
+    store: Box<dyn StateStore>,
+}
+
+impl Reaper {
+    pub fn new() -> Result<Self> {
+        let store: Box<dyn StateStore> = if dry_run() {
+            Box::<MemoryStore>::default()
+        } else {
+            Box::new(FileStore::xdg()?)
+        };
+        Ok(Reaper::with(new_client()?, store))
+    }
+
+    fn with(client: Box<dyn DockerClient>, store: Box<dyn StateStore>) -> Self {
+        Reaper { client, store }
+    }
+
+    /// stop the running devspace containers without sessions for longer than `timeout` at
+    /// the unix time `now`, returns their names
+    ///
+    /// Containers without a recorded idle time, like ones started by hand, count as idle
+    /// from the first call seeing them.
+    pub fn reap(&self, timeout: Duration, now: i64) -> Result<Vec<String>> {
+        let mut stopped = vec![];
+        for summary in self.client.list_all_containers(false)? {
+            if summary.state.as_deref() != Some("running") {
+                continue;
+            }
+            let project = summary.labels.as_ref().and_then(|l| l.get(PROJECT_KEY));
+            let name = summary
+                .names
+                .as_ref()
+                .and_then(|n| n.first())
+                .map(|n| n.trim_start_matches('/'));
+            let (Some(project), Some(name)) = (project, name) else {
+                continue;
+            };
+
+            let state = self.store.update(project, &mut |state| {
+                state.prune_sessions(alive);
+                if state.sessions.is_empty() {
+                    state.idle_since.get_or_insert(now);
+                }
+            });
+            // one broken state file must not keep the other containers running
+            let state = match state {
+                Ok(state) => state,
+                Err(e) => {
+                    warn!("skipping {}: {:#}", name, e);
+                    continue;
+                }
+            };
+            let Some(idle_since) = state.idle_since.filter(|_| state.sessions.is_empty()) else {
+                debug!("{} has {} session(s)", name, state.sessions.len());
+                continue;
+            };
+            if now - idle_since >= timeout.as_secs() as i64 {
+                self.client.stop_container(name)?;
+                stopped.push(name.to_string());
+            }
+        }
+        Ok(stopped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker::{client::tests::MockDockerClient, state::State};
+    use bollard::models::ContainerSummary;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn container(name: &str, project: &str, state: &str) -> ContainerSummary {
+        ContainerSummary {
+            names: Some(vec![format!("/{}", name)]),
+            state: Some(state.to_string()),
+            labels: Some(HashMap::from([(
+                PROJECT_KEY.to_string(),
+                project.to_string(),
+            )])),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_reap() {
+        let mut client = MockDockerClient::new();
+        client.expect_list_all_containers().returning(|_| {
+            Ok(vec![
+                container("idle", "1", "running"),
+                container("recent", "2", "running"),
+                container("busy", "3", "running"),
+                container("unknown", "4", "running"),
+                container("stopped", "5", "exited"),
+                container("broken", "6", "running"),
+            ])
+        });
+        client
+            .expect_stop_container()
+            .withf(|name| name == "idle")
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let tmpdir = TempDir::new().unwrap();
+        let store = FileStore::new(tmpdir.path().join("state"), tmpdir.path().join("run"));
+        let idle = |idle_since, sessions| State {
+            idle_since: Some(idle_since),
+            sessions,
+            ..Default::default()
+        };
+        store.save("1", &idle(1000, vec![])).unwrap();
+        store.save("2", &idle(4000, vec![])).unwrap();
+        store
+            .save("3", &idle(1000, vec![std::process::id()]))
+            .unwrap();
+        store.save("5", &idle(1000, vec![])).unwrap();
+        std::fs::write(tmpdir.path().join("state").join("6.json"), "{").unwrap();
+
+        let reaper = Reaper::with(Box::new(client), Box::new(store));
+        let stopped = reaper.reap(Duration::from_secs(3600), 5000).unwrap();
+        assert_eq!(stopped, ["idle"]);
+        assert_eq!(reaper.store.load("4").unwrap().idle_since, Some(5000));
+    }
+}
@@ -0,0 +1,101 @@
+use super::client::HostInfo;
+use crate::project::{HostRequirements, Size};
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// how the docker host falls short of `requirements`, resources it does not report are
+/// taken to be enough
+pub fn unmet(
+    requirements: &HostRequirements,
+    host: &HostInfo,
+    free_storage: Option<u64>,
+) -> Vec<String> {
+    let mut problems = vec![];
+    if let Some(cpus) = requirements
+        .cpus
+        .filter(|c| host.cpus != 0 && *c > host.cpus)
+    {
+        problems.push(format!(
+            "{} cpus needed, the docker host has {}",
+            cpus, host.cpus
+        ));
+    }
+    if let Some(memory) = requirements
+        .memory
+        .filter(|m| host.memory != 0 && m.0 > host.memory)
+    {
+        problems.push(format!(
+            "{} of memory needed, the docker host has {}",
+            memory,
+            Size(host.memory)
+        ));
+    }
+    if let (Some(storage), Some(free)) = (requirements.storage, free_storage) {
+        if storage.0 > free {
+            problems.push(format!(
+                "{} of storage needed, the docker host has {} free",
+                storage,
+                Size(free)
+            ));
+        }
+    }
+    problems
+}
+
+/// free bytes on the filesystem of `dir`, None if `dir` is not on this machine, like the
+/// root dir of a docker host in a vm
+pub fn free_space(dir: &Path) -> Option<u64> {
+    if !dir.exists() {
+        return None;
+    }
+    let output = Command::new("df")
+        .arg("-Pk")
+        .arg(dir)
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    // Filesystem 1024-blocks Used Available Capacity Mounted on
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let available = stdout.lines().nth(1)?.split_whitespace().nth(3)?;
+    available.parse::<u64>().ok().map(|kb| kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unmet() {
+        let host = HostInfo {
+            cpus: 4,
+            memory: 8 << 30,
+            root_dir: None,
+        };
+        let requirements = |cpus, memory: &str, storage: &str| HostRequirements {
+            cpus: Some(cpus),
+            memory: Some(Size::try_from(memory.to_string()).unwrap()),
+            storage: Some(Size::try_from(storage.to_string()).unwrap()),
+        };
+
+        assert!(unmet(&requirements(4, "8gb", "32gb"), &host, Some(32 << 30)).is_empty());
+        assert!(unmet(&requirements(4, "8gb", "32gb"), &host, None).is_empty());
+        assert_eq!(
+            unmet(&requirements(8, "16gb", "64gb"), &host, Some(1 << 30)),
+            [
+                "8 cpus needed, the docker host has 4",
+                "16gb of memory needed, the docker host has 8gb",
+                "64gb of storage needed, the docker host has 1gb free",
+            ]
+        );
+        assert!(unmet(&requirements(8, "16gb", "1kb"), &HostInfo::default(), None).is_empty());
+    }
+
+    #[test]
+    fn test_free_space() {
+        assert!(free_space(Path::new("/")).is_some_and(|f| f > 0));
+        assert_eq!(free_space(Path::new("/nonexistent/devspace")), None);
+    }
+}
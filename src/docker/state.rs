@@ -1,102 +1,385 @@
-// use anyhow::Result;
-// use serde::{Deserialize, Serialize};
-// use std::{fs, path::PathBuf};
-//
-// #[derive(Debug, Deserialize, Serialize, Default)]
-// struct Image {
-//     name: String,
-// }
-//
-// #[derive(Debug, Deserialize, Serialize, Default)]
-// struct State {
-//     #[serde(skip)]
-//     root: PathBuf,
-//
-//     image: Image,
-// }
-//
-// impl State {
-//     fn new<P: Into<PathBuf>>(root: P) -> Self {
-//         let root = root.into();
-//         State {
-//             root,
-//             ..Default::default()
-//         }
-//     }
-//
-//     fn save() -> Result<()> {
-//         Ok(())
-//     }
-//
-//     fn load() -> Result<Self> {
-//         Ok(State::default())
-//     }
-// }
-//
-// trait DiskStore {
-//     fn save_state(&self, state: &State) -> Result<()>;
-//     fn load_state(&self) -> Result<State>;
-// }
-//
-// const STATE_FILE: &str = "state.json";
-// const PREFIX: &str = "devspace";
-//
-// struct XDGStore {
-//     dirs: xdg::BaseDirectories,
-// }
-//
-// impl XDGStore {
-//     fn new() -> Result<Self> {
-//         Ok(XDGStore {
-//             dirs: xdg::BaseDirectories::with_prefix(PREFIX)?,
-//         })
-//     }
-// }
-//
-// impl DiskStore for XDGStore {
-//     fn save_state(&self, state: &State) -> Result<()> {
-//         let state_file = self.dirs.place_state_file(STATE_FILE)?;
-//         let f = fs::File::create(state_file)?;
-//         serde_json::to_writer(f, state)?;
-//         Ok(())
-//     }
-//
-//     fn load_state(&self) -> Result<State> {
-//         let state_file = self.dirs.place_state_file(STATE_FILE)?;
-//         let content = fs::read_to_string(state_file)?;
-//         Ok(serde_json::from_str(&content)?)
-//     }
-// }
-//
-// #[cfg(test)]
-// mod tests {
-//     use std::io::Write;
-//
-//     use super::*;
-//     use tempfile::NamedTempFile;
-//
-//     struct TestStore {
-//         state_file: NamedTempFile,
-//     }
-//
-//     impl TestStore {
-//         fn new() -> Self {
-//             TestStore {
-//                 state_file: NamedTempFile::new().unwrap(),
-//             }
-//         }
-//     }
-//
-//     impl DiskStore for TestStore {
-//         fn save_state(&self, state: &State) -> Result<()> {
-//             let json = serde_json::to_string(state)?;
-//             self.state_file.as_file().write_all(json.as_bytes())?;
-//             Ok(())
-//         }
-//
-//         fn load_state(&self) -> Result<State> {
-//             let state = serde_json::from_reader(self.state_file.reopen()?)?;
-//             Ok(state)
-//         }
-//     }
-// }
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs, io,
+    os::unix::fs::{DirBuilderExt, MetadataExt},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+const PREFIX: &str = "devspace";
+/// length of the project id prefix naming socket dirs, paths of unix sockets are limited to
+/// about 100 bytes
+const SOCKET_DIR_ID_LEN: usize = 12;
+/// where the socket dir of a project appears in its container
+pub const CONTAINER_SOCKET_DIR: &str = "/tmp/devspace/host";
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq, Eq)]
+pub struct State {
+    /// lifecycle steps already run in the current container
+    #[serde(default)]
+    pub lifecycle: Vec<String>,
+    /// pids of the devspace processes with a shell open in the container, once per shell
+    #[serde(default)]
+    pub sessions: Vec<u32>,
+    /// unix time since when no session is open
+    #[serde(default)]
+    pub idle_since: Option<i64>,
+}
+
+impl State {
+    /// forget the sessions of processes that exited without ending them
+    pub fn prune_sessions(&mut self, alive: impl Fn(u32) -> bool) {
+        self.sessions.retain(|pid| alive(*pid));
+    }
+}
+
+/// whether the process `pid` still runs
+pub fn alive(pid: u32) -> bool {
+    let proc = Path::new("/proc");
+    if proc.is_dir() {
+        return proc.join(pid.to_string()).exists();
+    }
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// bind mount of the socket dir `dir` in `--mount` form
+pub fn socket_mount(dir: &Path) -> String {
+    format!(
+        "type=bind,source={},target={}",
+        dir.display(),
+        CONTAINER_SOCKET_DIR
+    )
+}
+
+/// file name of the socket `name` served by this process, processes sharing a socket dir or a
+/// container each serve their own, so none removes the socket of another
+pub fn process_socket(name: &str) -> String {
+    format!("{}-{}.sock", name, std::process::id())
+}
+
+pub trait StateStore {
+    fn load(&self, project_name: &str) -> Result<State>;
+    fn save(&self, project_name: &str, state: &State) -> Result<()>;
+    /// file collecting the output of lifecycle commands, its directory exists
+    fn log_file(&self, project_name: &str) -> Result<PathBuf>;
+    /// directory for the sockets shared with the container, it exists
+    fn socket_dir(&self, project_name: &str) -> Result<PathBuf>;
+
+    /// load the state of a project, change it with `f` and save it
+    fn update(&self, project_name: &str, f: &mut dyn FnMut(&mut State)) -> Result<State> {
+        let mut state = self.load(project_name)?;
+        f(&mut state);
+        self.save(project_name, &state)?;
+        Ok(state)
+    }
+}
+
+/// keeps one json file per project in a directory, by default `$XDG_STATE_HOME/devspace`,
+/// and the socket dirs in a runtime directory with a short path
+pub struct FileStore {
+    dir: PathBuf,
+    runtime_dir: PathBuf,
+}
+
+impl FileStore {
+    pub fn new<P: Into<PathBuf>>(dir: P, runtime_dir: P) -> Self {
+        FileStore {
+            dir: dir.into(),
+            runtime_dir: runtime_dir.into(),
+        }
+    }
+
+    pub fn xdg() -> Result<Self> {
+        #[cfg(test)]
+        if let Some(fake) = super::fake::installed() {
+            let runtime_dir = fake.state_dir.join("run");
+            return Ok(FileStore::new(fake.state_dir, runtime_dir));
+        }
+        let dirs = xdg::BaseDirectories::with_prefix(PREFIX)?;
+        // the runtime dir is missing without a login session
+        let runtime_dir = match dirs.get_runtime_directory() {
+            Ok(dir) => dir.join(PREFIX),
+            Err(_) => {
+                // SAFETY: getuid has no preconditions and can not fail
+                let uid = unsafe { libc::getuid() };
+                let dir = std::env::temp_dir().join(format!("{}-{}", PREFIX, uid));
+                private_dir(&dir, uid).unwrap_or_else(|| dirs.get_state_home().join("run"))
+            }
+        };
+        Ok(FileStore::new(dirs.get_state_home(), runtime_dir))
+    }
+
+    fn state_file(&self, project_name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", project_name))
+    }
+
+    /// lock the state of a project against other processes until the returned file is closed
+    fn lock(&self, project_name: &str) -> Result<fs::File> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create state dir {:?}", &self.dir))?;
+        let lock_file = self.dir.join(format!("{}.lock", project_name));
+        let f = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_file)
+            .with_context(|| format!("failed to open lock file {:?}", &lock_file))?;
+        f.lock()
+            .with_context(|| format!("failed to lock {:?}", &lock_file))?;
+        Ok(f)
+    }
+}
+
+impl StateStore for FileStore {
+    fn load(&self, project_name: &str) -> Result<State> {
+        let state_file = self.state_file(project_name);
+        if !state_file.exists() {
+            return Ok(State::default());
+        }
+        let content = fs::read_to_string(&state_file)
+            .with_context(|| format!("failed to read state file {:?}", &state_file))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("invalid state file {:?}", &state_file))
+    }
+
+    fn save(&self, project_name: &str, state: &State) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create state dir {:?}", &self.dir))?;
+        let state_file = self.state_file(project_name);
+        // readers see the old or the new state, never a partly written one
+        let tmp_file = state_file.with_extension(format!("json.{}", std::process::id()));
+        let f = fs::File::create(&tmp_file)
+            .with_context(|| format!("failed to write state file {:?}", &tmp_file))?;
+        serde_json::to_writer(f, state)?;
+        fs::rename(&tmp_file, &state_file)
+            .with_context(|| format!("failed to write state file {:?}", &state_file))?;
+        Ok(())
+    }
+
+    fn update(&self, project_name: &str, f: &mut dyn FnMut(&mut State)) -> Result<State> {
+        let _lock = self.lock(project_name)?;
+        let mut state = self.load(project_name)?;
+        f(&mut state);
+        self.save(project_name, &state)?;
+        Ok(state)
+    }
+
+    fn log_file(&self, project_name: &str) -> Result<PathBuf> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create state dir {:?}", &self.dir))?;
+        Ok(self.dir.join(format!("{}.log", project_name)))
+    }
+
+    fn socket_dir(&self, project_name: &str) -> Result<PathBuf> {
+        let dir = self.runtime_dir.join(short_id(project_name));
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&dir)
+            .with_context(|| format!("failed to create {:?}", &dir))?;
+        Ok(dir)
+    }
+}
+
+/// `dir` if it is a directory only the user `uid` can access, creating it if missing, as
+/// anyone may have created it in a shared directory like the temporary one
+fn private_dir(dir: &Path, uid: u32) -> Option<PathBuf> {
+    match fs::DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(_) => return None,
+    }
+    let metadata = fs::symlink_metadata(dir).ok()?;
+    (metadata.is_dir() && metadata.uid() == uid && metadata.mode() & 0o777 == 0o700)
+        .then(|| dir.to_path_buf())
+}
+
+/// keeps states in memory only, for dry runs leaving no trace on the host, socket dirs are made
+/// in a temporary dir removed along with the store
+#[derive(Default)]
+pub struct MemoryStore {
+    states: RefCell<HashMap<String, State>>,
+    runtime_dir: RefCell<Option<PathBuf>>,
+}
+
+impl Drop for MemoryStore {
+    fn drop(&mut self) {
+        if let Some(dir) = self.runtime_dir.get_mut() {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+impl StateStore for MemoryStore {
+    fn load(&self, project_name: &str) -> Result<State> {
+        Ok(self
+            .states
+            .borrow()
+            .get(project_name)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn save(&self, project_name: &str, state: &State) -> Result<()> {
+        self.states
+            .borrow_mut()
+            .insert(project_name.to_string(), state.clone());
+        Ok(())
+    }
+
+    fn log_file(&self, _: &str) -> Result<PathBuf> {
+        Ok(PathBuf::from("/dev/null"))
+    }
+
+    fn socket_dir(&self, project_name: &str) -> Result<PathBuf> {
+        let mut runtime_dir = self.runtime_dir.borrow_mut();
+        let runtime_dir = match &mut *runtime_dir {
+            Some(dir) => dir,
+            None => {
+                static STORES: AtomicUsize = AtomicUsize::new(0);
+                let n = STORES.fetch_add(1, Ordering::Relaxed);
+                let name = format!("{}-{}-{}", PREFIX, std::process::id(), n);
+                let dir = std::env::temp_dir().join(name);
+                fs::DirBuilder::new()
+                    .mode(0o700)
+                    .create(&dir)
+                    .with_context(|| format!("failed to create {:?}", &dir))?;
+                runtime_dir.insert(dir)
+            }
+        };
+        let dir = runtime_dir.join(short_id(project_name));
+        fs::create_dir_all(&dir).with_context(|| format!("failed to create {:?}", &dir))?;
+        Ok(dir)
+    }
+}
+
+/// start of the project id naming its socket dir
+fn short_id(project_id: &str) -> &str {
+    project_id.get(..SOCKET_DIR_ID_LEN).unwrap_or(project_id)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use mockall::mock;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_file_store() {
+        let tmpdir = TempDir::new().unwrap();
+        let store = FileStore::new(tmpdir.path().join("state"), tmpdir.path().join("run"));
+
+        assert_eq!(store.load("aaa").unwrap(), State::default());
+
+        let state = State {
+            lifecycle: vec!["postCreateCommand".to_string()],
+            ..Default::default()
+        };
+        store.save("aaa", &state).unwrap();
+        assert_eq!(store.load("aaa").unwrap(), state);
+        assert_eq!(store.load("bbb").unwrap(), State::default());
+
+        let state = store.update("aaa", &mut |s| s.sessions.push(1)).unwrap();
+        assert_eq!(store.load("aaa").unwrap(), state);
+        let files: Vec<_> = fs::read_dir(tmpdir.path().join("state"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(files.len(), 2, "{:?}", files);
+
+        let log = store.log_file("aaa").unwrap();
+        assert_eq!(log, tmpdir.path().join("state").join("aaa.log"));
+        let dir = store.socket_dir("aaa").unwrap();
+        assert!(dir.is_dir());
+    }
+
+    #[test]
+    fn test_socket_dir() {
+        let tmpdir = TempDir::new().unwrap();
+        let home = tmpdir
+            .path()
+            .join("home")
+            .join("a-user-with-a-rather-long-name");
+        let store = FileStore::new(
+            home.join(".local").join("state").join(PREFIX),
+            tmpdir.path().join("run"),
+        );
+        let id = "8ee60da006ca5b7d3d0d7c1bd3f4ef2f0e9a8b3c6d7e8f9a0b1c2d3e4f5a6b7c";
+
+        let dir = store.socket_dir(id).unwrap();
+        assert_eq!(dir, tmpdir.path().join("run").join("8ee60da006ca"));
+        let mode = fs::metadata(&dir).unwrap().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        // binding fails with paths over the limit of unix sockets
+        let socket = dir.join("credential.sock");
+        std::os::unix::net::UnixListener::bind(&socket).unwrap();
+    }
+
+    #[test]
+    fn test_private_dir() {
+        let tmpdir = TempDir::new().unwrap();
+        let uid = fs::metadata(tmpdir.path()).unwrap().uid();
+        let dir = tmpdir.path().join("devspace-1000");
+
+        assert_eq!(private_dir(&dir, uid), Some(dir.clone()));
+        assert_eq!(fs::metadata(&dir).unwrap().mode() & 0o777, 0o700);
+        assert_eq!(private_dir(&dir, uid), Some(dir.clone()));
+        assert_eq!(private_dir(&dir, uid + 1), None);
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(private_dir(&dir, uid), None);
+
+        let link = tmpdir.path().join("link");
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700)).unwrap();
+        assert_eq!(private_dir(&link, uid), None);
+    }
+
+    #[test]
+    fn test_sessions() {
+        let store = MemoryStore::default();
+        let state = store
+            .update("aaa", &mut |s| s.sessions.extend([1, 2, 3]))
+            .unwrap();
+        assert_eq!(state.sessions, [1, 2, 3]);
+
+        let mut state = store.load("aaa").unwrap();
+        state.prune_sessions(|pid| pid != 2);
+        assert_eq!(state.sessions, [1, 3]);
+
+        assert!(alive(std::process::id()));
+        assert!(!alive(u32::MAX));
+    }
+
+    #[test]
+    fn test_memory_store_socket_dir() {
+        let store = MemoryStore::default();
+        let dir = store.socket_dir("aaa").unwrap();
+        assert!(dir.is_dir());
+        assert_eq!(store.socket_dir("aaa").unwrap(), dir);
+        assert!(store.socket_dir("bbb").unwrap().is_dir());
+
+        drop(store);
+        assert!(!dir.parent().unwrap().exists());
+    }
+
+    mock! {
+        pub StateStore {}
+
+        impl StateStore for StateStore {
+            fn load(&self, project_name: &str) -> Result<State>;
+            fn save(&self, project_name: &str, state: &State) -> Result<()>;
+            fn log_file(&self, project_name: &str) -> Result<PathBuf>;
+            fn socket_dir(&self, project_name: &str) -> Result<PathBuf>;
+        }
+    }
+}
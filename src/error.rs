@@ -0,0 +1,196 @@
+use crate::diagnostic::Diagnostic;
+use std::{fmt, path::PathBuf};
+use thiserror::Error;
+
+/// errors users can act on, each with a hint and its own process exit code
+#[derive(Debug, Error)]
+pub enum DevspaceError {
+    #[error("no devcontainer config found, searched:\n{}", list_paths(.searched))]
+    ConfigNotFound { searched: Vec<PathBuf> },
+
+    #[error("invalid config: {}", Problems(.0))]
+    ConfigInvalid(Vec<Diagnostic>),
+
+    #[error("unsupported config: {}", Problems(.0))]
+    ConfigUnsupported(Vec<Diagnostic>),
+
+    #[error("can not connect to the docker daemon: {message}")]
+    DockerUnreachable { message: String },
+
+    #[error("failed to build image {image}")]
+    ImageBuildFailed { image: String },
+
+    #[error("found {count} containers for project {project}")]
+    ContainerConflict { project: String, count: usize },
+
+    #[error("the docker host does not meet the hostRequirements of the project: {}", .problems.join(", "))]
+    HostRequirementsUnmet { problems: Vec<String> },
+
+    #[error("command {command:?} failed{}", ExitStatus(*.code))]
+    CommandFailed { command: String, code: Option<i32> },
+
+    /// the command of the user in `run` or `shell` failed, its exit code is passed on
+    #[error("command {command:?} failed{}", ExitStatus(*.code))]
+    UserCommandFailed { command: String, code: Option<i32> },
+}
+
+impl DevspaceError {
+    pub fn hint(&self) -> &'static str {
+        match self {
+            DevspaceError::ConfigNotFound { .. } => {
+                "create .devcontainer/devcontainer.json or point --root into a project"
+            }
+            DevspaceError::ConfigInvalid(_) => {
+                "fix the devcontainer config, see https://containers.dev/implementors/json_reference/"
+            }
+            DevspaceError::ConfigUnsupported(_) => {
+                "the config is valid, but devspace does not support this part of the spec yet"
+            }
+            DevspaceError::DockerUnreachable { .. } => {
+                "is the docker daemon running? check DOCKER_HOST and that you can run `docker ps`"
+            }
+            DevspaceError::ImageBuildFailed { .. } => {
+                "check the build output above, fix the Dockerfile and run the command again"
+            }
+            DevspaceError::ContainerConflict { .. } => {
+                "remove the extra containers with `docker rm`, keeping the one to use"
+            }
+            DevspaceError::HostRequirementsUnmet { .. } => {
+                "give the docker host more resources, or unset enforce_host_requirements to only warn"
+            }
+            DevspaceError::CommandFailed { .. } => "check the command output above",
+            DevspaceError::UserCommandFailed { .. } => {
+                "devspace exits with the exit code of the command"
+            }
+        }
+    }
+
+    /// the failed command of `err`, if any, as the command of the user, for `run` and `shell`
+    pub fn user_command(err: anyhow::Error) -> anyhow::Error {
+        match err.downcast_ref::<DevspaceError>() {
+            Some(DevspaceError::CommandFailed { command, code }) => {
+                DevspaceError::UserCommandFailed {
+                    command: command.clone(),
+                    code: *code,
+                }
+                .into()
+            }
+            _ => err,
+        }
+    }
+
+    /// process exit code, distinct per error, a failed command of the user exits with the
+    /// code of the command itself
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            DevspaceError::ConfigNotFound { .. } => 3,
+            DevspaceError::ConfigInvalid(_) => 4,
+            DevspaceError::DockerUnreachable { .. } => 5,
+            DevspaceError::ImageBuildFailed { .. } => 6,
+            DevspaceError::ContainerConflict { .. } => 7,
+            DevspaceError::HostRequirementsUnmet { .. } => 9,
+            DevspaceError::ConfigUnsupported(_) => 10,
+            DevspaceError::CommandFailed { .. } => 8,
+            DevspaceError::UserCommandFailed { code, .. } => code
+                .and_then(|c| u8::try_from(c).ok())
+                .filter(|c| *c != 0)
+                .unwrap_or(8),
+        }
+    }
+}
+
+fn list_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| format!("  {}", p.display()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+struct Problems<'a>(&'a [Diagnostic]);
+
+impl fmt::Display for Problems<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            [single] => write!(f, "{}", single),
+            all => {
+                write!(f, "{} problems found", all.len())?;
+                all.iter().try_for_each(|d| write!(f, "\n{}", d))
+            }
+        }
+    }
+}
+
+struct ExitStatus(Option<i32>);
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(code) => write!(f, " with exit code {}", code),
+            None => write!(f, ", terminated by signal"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let err = DevspaceError::ConfigNotFound {
+            searched: vec![PathBuf::from("/a/.devcontainer.json")],
+        };
+        assert_eq!(
+            err.to_string(),
+            "no devcontainer config found, searched:\n  /a/.devcontainer.json"
+        );
+
+        let err = DevspaceError::ConfigInvalid(vec![Diagnostic::new("image not specified")]);
+        assert_eq!(err.to_string(), "invalid config: image not specified");
+
+        let err = DevspaceError::ConfigInvalid(vec![Diagnostic::new("a"), Diagnostic::new("b")]);
+        assert_eq!(err.to_string(), "invalid config: 2 problems found\na\nb");
+
+        let err = DevspaceError::CommandFailed {
+            command: "docker exec".to_string(),
+            code: None,
+        };
+        assert_eq!(
+            err.to_string(),
+            "command \"docker exec\" failed, terminated by signal"
+        );
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let failed = |code| DevspaceError::CommandFailed {
+            command: "docker".to_string(),
+            code,
+        };
+        assert_eq!(failed(Some(3)).exit_code(), 8);
+        assert_eq!(failed(None).exit_code(), 8);
+
+        let user = |code| DevspaceError::user_command(failed(code).into());
+        let exit_code =
+            |err: anyhow::Error| err.downcast_ref::<DevspaceError>().unwrap().exit_code();
+        assert_eq!(exit_code(user(Some(130))), 130);
+        assert_eq!(exit_code(user(Some(-1))), 8);
+        assert_eq!(exit_code(user(None)), 8);
+        let unrelated = anyhow::anyhow!("oops");
+        assert_eq!(DevspaceError::user_command(unrelated).to_string(), "oops");
+
+        let unreachable = DevspaceError::DockerUnreachable {
+            message: "connection refused".to_string(),
+        };
+        assert_eq!(unreachable.exit_code(), 5);
+        assert!(unreachable
+            .hint()
+            .starts_with("is the docker daemon running?"));
+
+        let unmet = DevspaceError::HostRequirementsUnmet {
+            problems: vec!["8 cpus needed, the docker host has 4".to_string()],
+        };
+        assert_eq!(unmet.exit_code(), 9);
+    }
+}
@@ -0,0 +1,129 @@
+use clap::ValueEnum;
+use std::{fmt, io::IsTerminal};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::{
+    filter::{LevelFilter, Targets},
+    fmt::{format::Writer, FmtContext, FormatEvent, FormatFields},
+    prelude::*,
+    registry::LookupSpan,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// level of the devspace messages, `-q` leaves warnings and errors, every `-v` adds a level
+pub fn level(verbose: u8, quiet: bool) -> LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => LevelFilter::WARN,
+        (false, 0) => LevelFilter::INFO,
+        (false, 1) => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
+
+/// send messages to stderr, plain lines by default, with time, level and spans once verbose
+pub fn init(verbose: u8, quiet: bool, format: LogFormat) {
+    let level = level(verbose, quiet);
+    // dependencies like bollard only speak up at -vv
+    let dependencies = match level {
+        LevelFilter::TRACE => LevelFilter::DEBUG,
+        _ => LevelFilter::WARN,
+    };
+    let filter = Targets::new()
+        .with_target(env!("CARGO_PKG_NAME"), level)
+        .with_default(dependencies);
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal());
+
+    let registry = tracing_subscriber::registry();
+    match format {
+        LogFormat::Json => registry
+            .with(layer.json().with_span_list(true).with_filter(filter))
+            .init(),
+        LogFormat::Text if verbose > 0 => registry.with(layer.with_filter(filter)).init(),
+        LogFormat::Text => registry
+            .with(layer.event_format(Plain).with_filter(filter))
+            .init(),
+    }
+}
+
+/// just the message, warnings and errors prefixed like the errors printed by main
+struct Plain;
+
+impl<S, N> FormatEvent<S, N> for Plain
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        match *event.metadata().level() {
+            Level::ERROR => write!(writer, "error: ")?,
+            Level::WARN => write!(writer, "warning: ")?,
+            _ => {}
+        }
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+    use tracing::{info, info_span, warn};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_level() {
+        assert_eq!(level(0, true), LevelFilter::WARN);
+        assert_eq!(level(2, true), LevelFilter::WARN);
+        assert_eq!(level(0, false), LevelFilter::INFO);
+        assert_eq!(level(1, false), LevelFilter::DEBUG);
+        assert_eq!(level(3, false), LevelFilter::TRACE);
+    }
+
+    #[test]
+    fn test_plain() {
+        let buffer = Buffer::default();
+        let b = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .event_format(Plain)
+            .with_writer(move || b.clone())
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = info_span!("build", image = "aaa").entered();
+            info!("image does not exist, building...");
+            warn!("unknown key");
+        });
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            output,
+            "image does not exist, building...\nwarning: unknown key\n"
+        );
+    }
+}
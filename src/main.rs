@@ -1,15 +1,33 @@
 mod cli;
 mod config;
+mod diagnostic;
 mod docker;
+mod error;
+mod logging;
 mod project;
+mod schema;
+mod template;
+mod variables;
 
-use anyhow::Result;
+use error::DevspaceError;
+use std::process::ExitCode;
 
-// TODO: check if the error message is useer freindly
-fn main() -> Result<()> {
-    if let Err(e) = cli::run() {
-        eprintln!("error: {:#}", e);
-        return Err(e);
+fn main() -> ExitCode {
+    match cli::run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            // the command of the user reported its failure itself
+            if let Some(err @ DevspaceError::UserCommandFailed { .. }) = e.downcast_ref() {
+                return ExitCode::from(err.exit_code());
+            }
+            eprintln!("error: {:#}", e);
+            match e.downcast_ref::<DevspaceError>() {
+                Some(err) => {
+                    eprintln!("hint: {}", err.hint());
+                    ExitCode::from(err.exit_code())
+                }
+                None => ExitCode::FAILURE,
+            }
+        }
     }
-    Ok(())
 }
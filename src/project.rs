@@ -1,13 +1,101 @@
+use crate::{diagnostic::Diagnostic, error::DevspaceError, schema, variables::Variables};
 use anyhow::{anyhow, Context, Result};
-use jsonc_parser::parse_to_serde_value;
-use serde::Deserialize;
+use jsonc_parser::{ast, common::Ranged, parse_to_ast, parse_to_serde_value};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
 use std::{
-    io::{BufReader, Read},
-    path::PathBuf,
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
 };
 
-const CONFIG_DIR: &str = ".devcontainer";
-const CONFIG_FILE: &str = "devcontainer.json";
+pub const CONFIG_DIR: &str = ".devcontainer";
+pub const CONFIG_FILE: &str = "devcontainer.json";
+const ROOT_CONFIG_FILE: &str = ".devcontainer.json";
+const GIT_DIR: &str = ".git";
+const SHORT_ID_LEN: usize = 12;
+const DEFAULT_NAME: &str = "devspace";
+const WORKSPACES_DIR: &str = "/workspaces";
+pub const DEFAULT_SHELL: &str = "/bin/zsh";
+
+/// top-level properties of the devcontainer.json reference, supported or not, anything else
+/// is most likely a typo
+const KNOWN_KEYS: &[&str] = &[
+    "$schema",
+    "name",
+    "image",
+    "build",
+    "dockerFile",
+    "context",
+    "dockerComposeFile",
+    "service",
+    "runServices",
+    "workspaceFolder",
+    "workspaceMount",
+    "shutdownAction",
+    "overrideCommand",
+    "forwardPorts",
+    "portsAttributes",
+    "otherPortsAttributes",
+    "appPort",
+    "containerEnv",
+    "remoteEnv",
+    "containerUser",
+    "remoteUser",
+    "updateRemoteUserUID",
+    "userEnvProbe",
+    "mounts",
+    "runArgs",
+    "init",
+    "privileged",
+    "capAdd",
+    "securityOpt",
+    "initializeCommand",
+    "onCreateCommand",
+    "updateContentCommand",
+    "postCreateCommand",
+    "postStartCommand",
+    "postAttachCommand",
+    "waitFor",
+    "features",
+    "overrideFeatureInstallOrder",
+    "hostRequirements",
+    "secrets",
+    "customizations",
+    "extensions",
+    "settings",
+    // devspace extensions
+    "shell",
+];
+
+/// properties of the reference devspace does not apply to containers yet, the ones only
+/// meant for editors are left out
+const IGNORED_KEYS: &[&str] = &[
+    "context",
+    "overrideCommand",
+    "forwardPorts",
+    "portsAttributes",
+    "otherPortsAttributes",
+    "appPort",
+    "containerUser",
+    "remoteUser",
+    "updateRemoteUserUID",
+    "userEnvProbe",
+    "runArgs",
+    "init",
+    "privileged",
+    "capAdd",
+    "securityOpt",
+    "initializeCommand",
+    "onCreateCommand",
+    "updateContentCommand",
+    "postStartCommand",
+    "postAttachCommand",
+    "waitFor",
+    "features",
+    "overrideFeatureInstallOrder",
+];
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum ImageSource {
@@ -21,92 +109,579 @@ impl Default for ImageSource {
     }
 }
 
+/// what happens when the last shell of the container exits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShutdownAction {
+    None,
+    /// the default of the spec for image and Dockerfile configs
+    #[default]
+    StopContainer,
+    /// stop all containers of a compose project, only valid for compose configs, which are
+    /// not supported yet
+    StopCompose,
+}
+
+impl ShutdownAction {
+    pub fn stops(self) -> bool {
+        self != ShutdownAction::None
+    }
+}
+
+/// a size like `4gb` in the form of the spec, binary units
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Size(pub u64);
+
+impl TryFrom<String> for Size {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let unit_start = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (value, unit) = s.split_at(unit_start);
+        let shift = match unit {
+            "" => 0,
+            "kb" => 10,
+            "mb" => 20,
+            "gb" => 30,
+            "tb" => 40,
+            _ => return Err(format!("invalid size {:?}, expected e.g. 512mb or 4gb", s)),
+        };
+        value
+            .parse::<u64>()
+            .ok()
+            .and_then(|v| v.checked_mul(1 << shift))
+            .map(Size)
+            .ok_or_else(|| format!("invalid size {:?}", s))
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let units = [(40, "tb"), (30, "gb"), (20, "mb"), (10, "kb")];
+        match units
+            .iter()
+            .find(|(shift, _)| self.0 != 0 && self.0.is_multiple_of(1 << shift))
+        {
+            Some((shift, unit)) => write!(f, "{}{}", self.0 >> shift, unit),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+/// minimum resources of the docker host
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct HostRequirements {
+    pub cpus: Option<u64>,
+    pub memory: Option<Size>,
+    pub storage: Option<Size>,
+}
+
+/// tool specific settings, only the devspace ones are read
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Customizations {
+    #[serde(default)]
+    pub devspace: Limits,
+}
+
+/// resource limits of the container, a devspace extension
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Limits {
+    pub cpus: Option<f64>,
+    pub memory: Option<Size>,
+    /// `pids_limit` as named by docker compose is accepted as well
+    #[serde(alias = "pids_limit")]
+    pub pids_limit: Option<i64>,
+}
+
+/// a mount in the string form of `docker run --mount` or as object
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Mount {
+    Spec(String),
+    Object {
+        #[serde(rename = "type")]
+        typ: String,
+        source: Option<String>,
+        target: String,
+    },
+}
+
+impl fmt::Display for Mount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mount::Spec(spec) => write!(f, "{}", spec),
+            Mount::Object {
+                typ,
+                source: Some(source),
+                target,
+            } => write!(f, "type={},source={},target={}", typ, source, target),
+            Mount::Object {
+                typ,
+                source: None,
+                target,
+            } => write!(f, "type={},target={}", typ, target),
+        }
+    }
+}
+
+/// a lifecycle command, a string runs in `/bin/sh`, an array as is and an object holds named
+/// commands of either form
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum LifecycleCommand {
+    Shell(String),
+    Args(Vec<String>),
+    Named(BTreeMap<String, LifecycleCommand>),
+}
+
+impl LifecycleCommand {
+    /// the commands to run with their names, named commands run one after another in name
+    /// order instead of in parallel as the spec allows
+    pub fn commands(&self) -> Vec<(Option<&str>, Vec<&str>)> {
+        match self {
+            LifecycleCommand::Shell(script) => vec![(None, vec!["/bin/sh", "-c", script])],
+            LifecycleCommand::Args(args) => vec![(None, args.iter().map(|a| a.as_str()).collect())],
+            LifecycleCommand::Named(commands) => commands
+                .iter()
+                .flat_map(|(name, command)| {
+                    command
+                        .commands()
+                        .into_iter()
+                        .map(move |(_, args)| (Some(name.as_str()), args))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// name of a lifecycle step, or of a named command of it like `postCreateCommand.lint`
+pub fn step_name(step: &str, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("{}.{}", step, name),
+        None => step.to_string(),
+    }
+}
+
 #[derive(Debug, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Config {
-    name: Option<String>,
+    pub name: Option<String>,
     image: Option<String>,
     #[serde(rename = "dockerFile")]
     dockerfile: Option<String>,
     #[serde(skip)]
     pub image_source: ImageSource,
-    pub post_create_command: Option<Vec<String>>,
+    pub post_create_command: Option<LifecycleCommand>,
+    /// path of the workspace inside the container
+    pub workspace_folder: Option<String>,
+    /// mount of the project root, in `docker run --mount` form
+    pub workspace_mount: Option<String>,
+    pub shutdown_action: Option<ShutdownAction>,
+    #[serde(default)]
+    pub mounts: Vec<Mount>,
+    /// environment of the container
+    #[serde(default)]
+    pub container_env: BTreeMap<String, String>,
+    /// environment of commands run in the container, null unsets a variable
+    #[serde(default)]
+    pub remote_env: BTreeMap<String, Option<String>>,
+    /// shell started by `devspace shell`, a devspace extension
+    pub shell: Option<String>,
+    #[serde(default)]
+    pub host_requirements: HostRequirements,
+    #[serde(default)]
+    pub customizations: Customizations,
+    /// sha256 of the raw config content, used to detect config drift
+    #[serde(skip)]
+    pub hash: String,
+    /// problems that do not prevent using the config, like unknown keys
+    #[serde(skip)]
+    pub warnings: Vec<Diagnostic>,
 }
 
 impl Config {
-    pub fn new(input: impl Read) -> Result<Self> {
-        let mut reader = BufReader::new(input);
-        let mut content = String::new();
-        reader
-            .read_to_string(&mut content)
-            .context("config read failed")?;
-        let value =
-            parse_to_serde_value(&content, &Default::default()).context("config parse failed")?;
-
-        let config: Config = match value {
-            Some(value) => serde_json::from_value(value).context("config parse failed")?,
-            None => return Err(anyhow!("invalid config")),
+    /// parse a config without knowing its location, variables are not substituted
+    #[cfg(test)]
+    pub fn new(input: impl std::io::Read) -> Result<Self> {
+        let content = std::io::read_to_string(input).context("config read failed")?;
+        Config::parse(&content, None)
+    }
+
+    /// read and parse the config at `location`, errors and warnings point into the file and
+    /// variables like `${localWorkspaceFolder}` are substituted
+    pub fn from_file(location: &ConfigLocation) -> Result<Self> {
+        let path = &location.file;
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to open config file {:?}", path))?;
+        let in_file = |diagnostics: Vec<Diagnostic>| -> Vec<Diagnostic> {
+            diagnostics.into_iter().map(|d| d.with_file(path)).collect()
+        };
+        let mut config = Config::parse(&content, Some(location)).map_err(|e| match e
+            .downcast::<DevspaceError>()
+        {
+            Ok(DevspaceError::ConfigInvalid(diagnostics)) => {
+                DevspaceError::ConfigInvalid(in_file(diagnostics)).into()
+            }
+            Ok(DevspaceError::ConfigUnsupported(diagnostics)) => {
+                DevspaceError::ConfigUnsupported(in_file(diagnostics)).into()
+            }
+            Ok(e) => e.into(),
+            Err(e) => e.context(format!("invalid config file {:?}", path)),
+        })?;
+        config.warnings = config
+            .warnings
+            .into_iter()
+            .map(|w| w.with_file(path))
+            .collect();
+        Ok(config)
+    }
+
+    fn parse(content: &str, location: Option<&ConfigLocation>) -> Result<Self> {
+        let value = parse_to_serde_value(content, &Default::default()).map_err(|e| {
+            DevspaceError::ConfigInvalid(vec![Diagnostic::at(e.message, content, e.range.start)])
+        })?;
+        let properties = top_level_properties(content);
+
+        let mut config: Config = match value {
+            Some(mut value) => {
+                let violations = schema::validate(&value, content);
+                if !violations.is_empty() {
+                    return Err(DevspaceError::ConfigInvalid(violations).into());
+                }
+                let unsupported: Vec<Diagnostic> = properties
+                    .iter()
+                    .filter_map(|p| {
+                        unsupported_source(&p.key).map(|m| Diagnostic::at(m, content, p.key_offset))
+                    })
+                    .collect();
+                if !unsupported.is_empty() {
+                    return Err(DevspaceError::ConfigUnsupported(unsupported).into());
+                }
+                if let Some(location) = location {
+                    substitute_variables(&mut value, location);
+                }
+                serde_json::from_value(value.clone())
+                    .map_err(|e| locate_error(e, &value, &properties, content))?
+            }
+            None => return Err(invalid_config("empty config").into()),
         };
+        // compose configs are rejected as unsupported above
+        if config.shutdown_action == Some(ShutdownAction::StopCompose) {
+            let offset = properties
+                .iter()
+                .find(|p| p.key == "shutdownAction")
+                .map_or(0, |p| p.value_offset);
+            let message =
+                r#""stopCompose" only applies to "dockerComposeFile" configs, use "stopContainer""#;
+            return Err(DevspaceError::ConfigInvalid(vec![Diagnostic::at(
+                message, content, offset,
+            )])
+            .into());
+        }
+        config.hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        config.warnings = properties
+            .iter()
+            .filter_map(|p| {
+                unknown_key(&p.key)
+                    .or_else(|| ignored_key(&p.key))
+                    .map(|m| Diagnostic::at(m, content, p.key_offset))
+            })
+            .collect();
         normalize_config(config)
     }
 }
 
+/// a top-level config property and where its key and value start
+struct Property {
+    key: String,
+    key_offset: usize,
+    value_offset: usize,
+}
+
+/// top-level object properties, empty if the content is not an object
+fn top_level_properties(content: &str) -> Vec<Property> {
+    let ast = parse_to_ast(content, &Default::default(), &Default::default());
+    match ast.ok().and_then(|r| r.value) {
+        Some(ast::Value::Object(object)) => object
+            .properties
+            .iter()
+            .map(|p| Property {
+                key: p.name.as_str().to_string(),
+                key_offset: p.name.range().start,
+                value_offset: p.value.range().start,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// serde errors of `from_value` carry no position, find the first property that fails to
+/// deserialize on its own and point at its value
+fn locate_error(
+    err: serde_json::Error,
+    value: &Value,
+    properties: &[Property],
+    content: &str,
+) -> DevspaceError {
+    let failing = properties.iter().find_map(|p| {
+        let single = Map::from_iter([(p.key.clone(), value.get(&p.key)?.clone())]);
+        let err = serde_json::from_value::<Config>(Value::Object(single)).err()?;
+        Some(Diagnostic::at(
+            format!("{:?}: {}", p.key, err),
+            content,
+            p.value_offset,
+        ))
+    });
+    DevspaceError::ConfigInvalid(vec![failing.unwrap_or_else(|| Diagnostic::new(err))])
+}
+
+/// image sources of the spec devspace can not create containers from yet
+fn unsupported_source(key: &str) -> Option<String> {
+    let alternative = match key {
+        "build" => r#"use "dockerFile""#,
+        "dockerComposeFile" => r#"use "image" or "dockerFile""#,
+        _ => return None,
+    };
+    Some(format!("{:?} is not supported yet, {}", key, alternative))
+}
+
+fn ignored_key(key: &str) -> Option<String> {
+    IGNORED_KEYS
+        .contains(&key)
+        .then(|| format!("{:?} is not supported yet and ignored", key))
+}
+
+fn unknown_key(key: &str) -> Option<String> {
+    if KNOWN_KEYS.contains(&key) {
+        return None;
+    }
+    let suggestion = KNOWN_KEYS
+        .iter()
+        .map(|k| {
+            (
+                strsim::levenshtein(&key.to_lowercase(), &k.to_lowercase()),
+                k,
+            )
+        })
+        .filter(|(distance, _)| *distance <= 2)
+        .min();
+    Some(match suggestion {
+        Some((_, k)) => format!("unknown key {:?}, did you mean {:?}?", key, k),
+        None => format!("unknown key {:?}", key),
+    })
+}
+
+/// substitute `${...}` variables, `${containerWorkspaceFolder}` refers to the configured
+/// workspace folder after substituting the local variables in it
+fn substitute_variables(value: &mut Value, location: &ConfigLocation) {
+    let mut vars = Variables::new(
+        &location.root,
+        &devcontainer_id(&location.root, &location.file),
+    );
+    let workspace_folder = match value.get("workspaceFolder").and_then(|v| v.as_str()) {
+        Some(folder) => vars.substitute_str(folder),
+        None => default_workspace_folder(&location.root),
+    };
+    vars.container_workspace_folder = Some(workspace_folder);
+    vars.substitute(value);
+}
+
+fn default_workspace_folder(root: &Path) -> String {
+    let basename = root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("{}/{}", WORKSPACES_DIR, basename)
+}
+
 fn normalize_config(mut config: Config) -> Result<Config> {
     if let Some(image) = &config.image {
         config.image_source = ImageSource::Image(image.to_owned());
     } else if let Some(docker_file) = &config.dockerfile {
-        config.image_source = ImageSource::Dockerfile(
-            PathBuf::from(CONFIG_DIR)
-                .join(docker_file)
-                .display()
-                .to_string(),
-        );
+        config.image_source = ImageSource::Dockerfile(docker_file.to_owned());
     } else {
-        return Err(anyhow!("invalid config, dockerfile or image not specified"));
+        return Err(invalid_config("dockerfile or image not specified").into());
     }
 
     Ok(config)
 }
 
+fn invalid_config(message: impl ToString) -> DevspaceError {
+    DevspaceError::ConfigInvalid(vec![Diagnostic::new(message)])
+}
+
 #[derive(Debug, Default)]
 pub struct Project {
     pub root: PathBuf,
-    pub config_dir: PathBuf,
     pub config_file: PathBuf,
+    /// friendly name for output
     pub name: String,
+    /// stable id derived from the project root and config path
+    pub id: String,
+    /// docker container name, unique per project and config
+    pub container_name: String,
+    /// docker image repository name, unique per project and config
+    pub image_name: String,
+    /// path of the project root inside the container
+    pub workspace_folder: String,
+    /// mount of the project root into the container
+    pub workspace_mount: String,
     pub config: Config,
 }
 
+/// where a devcontainer config was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigLocation {
+    pub root: PathBuf,
+    pub file: PathBuf,
+    /// name of a `.devcontainer/<name>/devcontainer.json` config, None for the default ones
+    pub name: Option<String>,
+}
+
+impl ConfigLocation {
+    fn new(root: &Path, file: PathBuf, name: Option<String>) -> Self {
+        ConfigLocation {
+            root: root.to_path_buf(),
+            file,
+            name,
+        }
+    }
+
+    /// locate a config file given by path, the project root is derived from the
+    /// `.devcontainer` layout and falls back to `default_root`
+    fn from_path(file: PathBuf, default_root: &Path) -> Self {
+        let parent = file.parent().unwrap_or(Path::new("/"));
+        let is_config_dir = |p: &Path| p.file_name().is_some_and(|n| n == CONFIG_DIR);
+
+        if file.file_name().is_some_and(|n| n == ROOT_CONFIG_FILE) {
+            ConfigLocation::new(parent, file.clone(), None)
+        } else if is_config_dir(parent) {
+            let root = parent.parent().unwrap_or(default_root);
+            ConfigLocation::new(root, file.clone(), None)
+        } else if let Some(config_dir) = parent.parent().filter(|p| is_config_dir(p)) {
+            let root = config_dir.parent().unwrap_or(default_root);
+            let name = parent.file_name().map(|n| n.to_string_lossy().to_string());
+            ConfigLocation::new(root, file.clone(), name)
+        } else {
+            ConfigLocation::new(default_root, file, None)
+        }
+    }
+}
+
+impl fmt::Display for ConfigLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.file.strip_prefix(&self.root).unwrap_or(&self.file);
+        match &self.name {
+            Some(name) => write!(f, "{} ({})", name, path.display()),
+            None => write!(f, "default ({})", path.display()),
+        }
+    }
+}
+
 impl Project {
-    fn new<P: Into<PathBuf>>(root: P, config: Config) -> Result<Self> {
-        let root = root.into();
-        let name = match &config.name {
+    fn new(location: &ConfigLocation, mut config: Config) -> Result<Self> {
+        let root = location.root.clone();
+        let config_file = location.file.clone();
+        let mut name = match &config.name {
             Some(name) => name.to_string(),
             None => get_project_name(&root)?,
+        };
+        if let Some(config_name) = &location.name {
+            name = format!("{}_{}", name, config_name);
         }
-        .replace(' ', "_");
-        let config_dir = root.join(CONFIG_DIR);
-        let config_file = config_dir.join(CONFIG_FILE);
+        let name = name.replace(' ', "_");
+        let config_dir = config_file
+            .parent()
+            .ok_or_else(|| anyhow!("invalid config path {:?}", &config_file))?
+            .to_path_buf();
+
+        // paths in the config are relative to the config file
+        if let ImageSource::Dockerfile(dockerfile) = &config.image_source {
+            config.image_source =
+                ImageSource::Dockerfile(config_dir.join(dockerfile).display().to_string());
+        }
+
+        let id = devcontainer_id(&root, &config_file);
+        let short_id = &id[..SHORT_ID_LEN];
+        let container_name = format!("{}-{}", container_name(&name), short_id);
+        let image_name = format!("{}-{}", image_name(&name), short_id);
+        let workspace_folder = config
+            .workspace_folder
+            .clone()
+            .unwrap_or_else(|| default_workspace_folder(&root));
+        let workspace_mount = config.workspace_mount.clone().unwrap_or_else(|| {
+            format!(
+                "type=bind,source={},target={}",
+                root.display(),
+                workspace_folder
+            )
+        });
 
         Ok(Self {
             root,
-            config_dir,
             config_file,
             name,
+            id,
+            container_name,
+            image_name,
+            workspace_folder,
+            workspace_mount,
             config,
         })
     }
+
+    /// the workspace mount followed by the mounts of the config, in `--mount` form
+    pub fn mounts(&self) -> Vec<String> {
+        let mut mounts = vec![self.workspace_mount.clone()];
+        mounts.extend(self.config.mounts.iter().map(|m| m.to_string()));
+        mounts
+    }
+
+    /// containerEnv as `KEY=value` pairs
+    pub fn container_env(&self) -> Vec<String> {
+        self.config
+            .container_env
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect()
+    }
+
+    /// remoteEnv as `KEY=value` pairs, variables set to null are left out
+    pub fn remote_env(&self) -> Vec<String> {
+        self.config
+            .remote_env
+            .iter()
+            .filter_map(|(k, v)| v.as_ref().map(|v| format!("{}={}", k, v)))
+            .collect()
+    }
+
+    /// tag of the image built from the project Dockerfile
+    pub fn image_tag(&self) -> String {
+        format!("{}:latest", self.image_name)
+    }
+
+    /// load the project containing `start`, `selector` is the name or path of the config to
+    /// use, `choose` picks one when several configs are found and there is no selector
+    pub fn load<F>(start: &Path, selector: Option<&str>, choose: F) -> Result<Self>
+    where
+        F: FnOnce(&[ConfigLocation]) -> Result<usize>,
+    {
+        let start = std::fs::canonicalize(start)
+            .with_context(|| format!("invalid project root {:?}", start))?;
+        let location = select_config(&start, selector, choose)?;
+
+        let config = Config::from_file(&location)?;
+        Project::new(&location, config)
+    }
 }
 
 impl TryFrom<&PathBuf> for Project {
     type Error = anyhow::Error;
 
-    fn try_from(root: &PathBuf) -> Result<Self> {
-        let c = root.join(CONFIG_DIR).join(CONFIG_FILE);
-        let f = std::fs::File::open(&c)
-            .with_context(|| format!("failed to open config file {:?}", &c))?;
-        let config = Config::new(f)?;
-        Project::new(root, config)
+    fn try_from(start: &PathBuf) -> Result<Self> {
+        Project::load(start, None, |configs| Err(ambiguous_configs(configs)))
     }
 }
 
@@ -124,6 +699,159 @@ impl AsRef<Project> for Project {
     }
 }
 
+pub fn ambiguous_configs(configs: &[ConfigLocation]) -> anyhow::Error {
+    let names = configs
+        .iter()
+        .map(|c| format!("  {}", c))
+        .collect::<Vec<_>>()
+        .join("\n");
+    anyhow!(
+        "multiple devcontainer configs found, select one with --config:\n{}",
+        names
+    )
+}
+
+fn select_config<F>(start: &Path, selector: Option<&str>, choose: F) -> Result<ConfigLocation>
+where
+    F: FnOnce(&[ConfigLocation]) -> Result<usize>,
+{
+    if let Some(path) = selector.filter(|s| is_config_path(s)) {
+        let root = find_configs(start)
+            .ok()
+            .and_then(|configs| configs.first().map(|c| c.root.clone()))
+            .unwrap_or_else(|| start.to_path_buf());
+        let file = std::fs::canonicalize(root.join(path))
+            .with_context(|| format!("devcontainer config file {:?} not found", path))?;
+        return Ok(ConfigLocation::from_path(file, &root));
+    }
+
+    let mut configs = find_configs(start)?;
+    let index = match selector {
+        Some(selector) => configs
+            .iter()
+            .position(|c| c.name.as_deref() == Some(selector))
+            .ok_or_else(|| {
+                let names = configs.iter().filter_map(|c| c.name.as_deref());
+                anyhow!(
+                    "devcontainer config {:?} not found, available: {}",
+                    selector,
+                    names.collect::<Vec<_>>().join(", ")
+                )
+            })?,
+        None if configs.len() == 1 => 0,
+        None => choose(&configs)?,
+    };
+    if index >= configs.len() {
+        return Err(anyhow!("invalid devcontainer config selection"));
+    }
+    Ok(configs.swap_remove(index))
+}
+
+/// a selector is a path rather than a config name if it has a separator or a .json extension,
+/// relative paths are resolved against the project root
+fn is_config_path(selector: &str) -> bool {
+    selector.contains(std::path::is_separator) || selector.ends_with(".json")
+}
+
+/// search `start` and its parents for devcontainer configs, stopping at the filesystem root
+/// or at the first directory containing `.git`, returns all configs of the closest directory
+/// that has any
+pub fn find_configs(start: &Path) -> Result<Vec<ConfigLocation>> {
+    let mut searched = vec![];
+    for dir in start.ancestors() {
+        let configs = configs_in(dir, &mut searched);
+        if !configs.is_empty() {
+            return Ok(configs);
+        }
+
+        if dir.join(GIT_DIR).exists() {
+            break;
+        }
+    }
+
+    Err(DevspaceError::ConfigNotFound { searched }.into())
+}
+
+fn configs_in(dir: &Path, searched: &mut Vec<PathBuf>) -> Vec<ConfigLocation> {
+    let config_dir = dir.join(CONFIG_DIR);
+    let mut candidates = vec![
+        (config_dir.join(CONFIG_FILE), None),
+        (dir.join(ROOT_CONFIG_FILE), None),
+    ];
+    if let Ok(entries) = std::fs::read_dir(&config_dir) {
+        let mut names: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .filter_map(|e| e.file_name().to_str().map(|n| n.to_string()))
+            .collect();
+        names.sort();
+        for name in names {
+            candidates.push((config_dir.join(&name).join(CONFIG_FILE), Some(name)));
+        }
+    }
+    let configs = candidates
+        .into_iter()
+        .filter_map(|(file, name)| {
+            if file.is_file() {
+                Some(ConfigLocation::new(dir, file, name))
+            } else {
+                if name.is_none() {
+                    searched.push(file);
+                }
+                None
+            }
+        })
+        .collect();
+    searched.push(config_dir.join("*").join(CONFIG_FILE));
+    configs
+}
+
+fn devcontainer_id(root: &Path, config_file: &Path) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(root.as_os_str().as_encoded_bytes());
+    hasher.update([0]);
+    hasher.update(config_file.as_os_str().as_encoded_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// docker container names must match `[a-zA-Z0-9][a-zA-Z0-9_.-]*`
+fn container_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '.' | '-' => c,
+            _ => '_',
+        })
+        .collect();
+    let name = name.trim_start_matches(['_', '.', '-']);
+    if name.is_empty() {
+        DEFAULT_NAME.to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// docker image names are lowercase alphanumerics separated by single `.`, `_` or `-`
+fn image_name(name: &str) -> String {
+    let mut result = String::new();
+    for c in name.to_lowercase().chars() {
+        match c {
+            'a'..='z' | '0'..='9' => result.push(c),
+            '.' | '_' | '-' if !result.is_empty() && !result.ends_with(['.', '_', '-']) => {
+                result.push(c)
+            }
+            _ if !result.is_empty() && !result.ends_with(['.', '_', '-']) => result.push('-'),
+            _ => {}
+        }
+    }
+    let result = result.trim_end_matches(['.', '_', '-']);
+    if result.is_empty() {
+        DEFAULT_NAME.to_string()
+    } else {
+        result.to_string()
+    }
+}
+
 fn get_project_name(root: &PathBuf) -> Result<String> {
     let root = PathBuf::from(root);
     let name = root.file_name().ok_or_else(|| anyhow!("invalid path"))?;
@@ -145,21 +873,85 @@ pub mod tests {
         {
             "name": "test",
             "dockerFile": "Dockerfile",
-            "postCreateCommand": ["echo", "hello"]
+            "postCreateCommand": ["echo", "hello"],
+            "shutdownAction": "none"
         }"#;
 
         let config = Config::new(json.as_bytes()).unwrap();
         assert_eq!(config.name.unwrap(), "test");
+        assert_eq!(config.shutdown_action, Some(ShutdownAction::None));
+        assert!(!ShutdownAction::None.stops());
         assert_eq!(
             config.image_source,
-            ImageSource::Dockerfile(
-                PathBuf::from(CONFIG_DIR)
-                    .join("Dockerfile")
-                    .display()
-                    .to_string()
-            )
+            ImageSource::Dockerfile("Dockerfile".to_string())
         );
-        assert_eq!(config.post_create_command.unwrap(), ["echo", "hello"]);
+        let command = config.post_create_command.unwrap();
+        assert_eq!(command.commands(), [(None, vec!["echo", "hello"])]);
+    }
+
+    #[test]
+    fn test_lifecycle_command() {
+        let json = r#"
+        {
+            "image": "test",
+            "postCreateCommand": {
+                "tools": "go install gopls",
+                "lint": ["go", "vet"]
+            }
+        }"#;
+        let config = Config::new(json.as_bytes()).unwrap();
+        assert_eq!(
+            config.post_create_command.unwrap().commands(),
+            [
+                (Some("lint"), vec!["go", "vet"]),
+                (Some("tools"), vec!["/bin/sh", "-c", "go install gopls"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_resources() {
+        let json = r#"
+        {
+            "image": "test",
+            "hostRequirements": { "cpus": 2, "memory": "4gb", "storage": "32gb" },
+            "customizations": {
+                "vscode": { "extensions": [] },
+                "devspace": { "cpus": 1.5, "memory": "512mb", "pidsLimit": 256 }
+            }
+        }"#;
+
+        let config = Config::new(json.as_bytes()).unwrap();
+        assert_eq!(
+            config.host_requirements,
+            HostRequirements {
+                cpus: Some(2),
+                memory: Some(Size(4 << 30)),
+                storage: Some(Size(32 << 30)),
+            }
+        );
+        let limits = &config.customizations.devspace;
+        assert_eq!(limits.cpus, Some(1.5));
+        assert_eq!(limits.memory, Some(Size(512 << 20)));
+        assert_eq!(limits.pids_limit, Some(256));
+
+        let json = r#"{ "image": "test", "customizations": { "devspace": { "pids_limit": 1 } } }"#;
+        let config = Config::new(json.as_bytes()).unwrap();
+        assert_eq!(config.customizations.devspace.pids_limit, Some(1));
+        let json = r#"{ "image": "test", "customizations": { "devspace": { "pids_limit": 0 } } }"#;
+        assert!(Config::new(json.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_size() {
+        let size = |s: &str| Size::try_from(s.to_string());
+        assert_eq!(size("1024"), Ok(Size(1024)));
+        assert_eq!(size("4gb"), Ok(Size(4 << 30)));
+        assert!(size("4g").is_err());
+        assert!(size("99999999999tb").is_err());
+        assert_eq!(Size(4 << 30).to_string(), "4gb");
+        assert_eq!(Size(1536 << 20).to_string(), "1536mb");
+        assert_eq!(Size(1000).to_string(), "1000");
     }
 
     #[test]
@@ -177,6 +969,18 @@ pub mod tests {
             ImageSource::Image("test image".to_string())
         );
         assert_eq!(config.name, None);
+        assert_eq!(config.shutdown_action, None);
+    }
+
+    #[test]
+    fn test_config_hash() {
+        let json = r#"{ "image": "test" }"#;
+        let config = Config::new(json.as_bytes()).unwrap();
+        assert_eq!(config.hash, Config::new(json.as_bytes()).unwrap().hash);
+        assert_eq!(config.hash.len(), 64);
+
+        let other = Config::new(r#"{ "image": "test2" }"#.as_bytes()).unwrap();
+        assert_ne!(config.hash, other.hash);
     }
 
     #[test]
@@ -192,16 +996,135 @@ pub mod tests {
         Config::new(json.as_bytes()).unwrap();
     }
 
+    #[test]
+    fn test_config_invalid_position() {
+        let json = "{\n  \"image\": \"test\",\n  \"name\": }";
+        let err = Config::new(json.as_bytes()).unwrap_err();
+        match err.downcast_ref::<DevspaceError>() {
+            Some(DevspaceError::ConfigInvalid(diagnostics)) => {
+                let l = diagnostics[0].location.as_ref().unwrap();
+                assert_eq!((l.line, l.column), (3, 11));
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_config_invalid_type_position() {
+        // a size the schema accepts but that does not fit
+        let json =
+            "{\n  \"image\": \"test\",\n  \"hostRequirements\": { \"memory\": \"99999999999999999999gb\" }\n}";
+        let err = Config::new(json.as_bytes()).unwrap_err();
+        match err.downcast_ref::<DevspaceError>() {
+            Some(DevspaceError::ConfigInvalid(diagnostics)) => {
+                let d = &diagnostics[0];
+                assert!(d.message.starts_with("\"hostRequirements\": invalid size"));
+                let l = d.location.as_ref().unwrap();
+                assert_eq!((l.line, l.column), (3, 23));
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_config_unsupported_source() {
+        let json = "{\n  \"dockerComposeFile\": \"compose.yml\",\n  \"service\": \"app\",\n  \"workspaceFolder\": \"/app\"\n}";
+        let err = Config::new(json.as_bytes()).unwrap_err();
+        match err.downcast_ref::<DevspaceError>() {
+            Some(DevspaceError::ConfigUnsupported(diagnostics)) => {
+                let d = &diagnostics[0];
+                assert_eq!(
+                    d.message,
+                    r#""dockerComposeFile" is not supported yet, use "image" or "dockerFile""#
+                );
+                let l = d.location.as_ref().unwrap();
+                assert_eq!((l.line, l.column), (2, 3));
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        let json = r#"{ "build": { "dockerfile": "Dockerfile" } }"#;
+        let err = Config::new(json.as_bytes()).unwrap_err();
+        assert_eq!(err.downcast_ref::<DevspaceError>().unwrap().exit_code(), 10);
+    }
+
+    #[test]
+    fn test_config_stop_compose() {
+        let json = "{\n  \"image\": \"alpine\",\n  \"shutdownAction\": \"stopCompose\"\n}";
+        let err = Config::new(json.as_bytes()).unwrap_err();
+        match err.downcast_ref::<DevspaceError>() {
+            Some(DevspaceError::ConfigInvalid(diagnostics)) => {
+                let d = &diagnostics[0];
+                assert!(d
+                    .message
+                    .starts_with(r#"shutdownAction: "stopCompose" is not one of"#));
+                let l = d.location.as_ref().unwrap();
+                assert_eq!((l.line, l.column), (3, 21));
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_config_unknown_keys() {
+        let json = r#"{
+            "image": "test",
+            "postCreatCommand": ["echo"],
+            "forwardPorts": [3000],
+            "foo": 1
+        }"#;
+        let config = Config::new(json.as_bytes()).unwrap();
+        let warnings: Vec<_> = config.warnings.iter().map(|w| &w.message).collect();
+        assert_eq!(
+            warnings,
+            [
+                "unknown key \"postCreatCommand\", did you mean \"postCreateCommand\"?",
+                "\"forwardPorts\" is not supported yet and ignored",
+                "unknown key \"foo\"",
+            ]
+        );
+        assert_eq!(config.warnings[0].location.as_ref().unwrap().line, 3);
+    }
+
+    #[test]
+    fn test_config_from_file() {
+        let tmp_project = TmpProjectDir::new("aaa").devcontainer_json(r#"{ "image": 1 }"#);
+        let file = tmp_project.root.join(CONFIG_DIR).join(CONFIG_FILE);
+        let location = ConfigLocation::from_path(file.clone(), &tmp_project.root);
+        let err = Config::from_file(&location).unwrap_err();
+        match err.downcast_ref::<DevspaceError>() {
+            Some(DevspaceError::ConfigInvalid(diagnostics)) => {
+                assert_eq!(diagnostics[0].file, Some(file))
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
     #[test]
     fn test_validate_config() {
+        // the schema rejects configs with both, image still wins when normalizing
+        let config = Config {
+            image: Some("test".to_string()),
+            dockerfile: Some("Dockerfile".to_string()),
+            ..Default::default()
+        };
+        let config = normalize_config(config).unwrap();
+        assert_eq!(config.image_source, ImageSource::Image("test".to_string()));
+    }
+
+    #[test]
+    fn test_config_schema_violations() {
         let json = r#"
         {
             "image": "test",
             "dockerFile": "Dockerfile",
-            "postCreateCommand": ["echo", "hello"],
+            "shutdownAction": "halt",
         }"#;
-        let config = normalize_config(Config::new(json.as_bytes()).unwrap()).unwrap();
-        assert_eq!(config.image_source, ImageSource::Image("test".to_string()));
+        let err = Config::new(json.as_bytes()).unwrap_err();
+        match err.downcast_ref::<DevspaceError>() {
+            Some(DevspaceError::ConfigInvalid(diagnostics)) => assert_eq!(diagnostics.len(), 2),
+            other => panic!("unexpected error {:?}", other),
+        }
     }
 
     #[test]
@@ -224,6 +1147,7 @@ pub mod tests {
         let tmp_project = TmpProjectDir::new("xxxx xxx").devcontainer_json(json.as_bytes());
         let project = Project::try_from(&tmp_project.root).unwrap();
         assert_eq!(project.name, "xxxx_xxx");
+        assert!(project.root.ends_with(&tmp_project.name));
     }
 
     #[test]
@@ -240,6 +1164,261 @@ pub mod tests {
         assert_eq!(project.name, "taskcommander_dev");
     }
 
+    #[test]
+    fn test_project_dockerfile_relative_to_config() {
+        let json = r#"{ "dockerFile": "Dockerfile" }"#;
+        let tmp_project = TmpProjectDir::new("aaa").devcontainer_json(json);
+        let project = Project::try_from(&tmp_project.root).unwrap();
+        let root = std::fs::canonicalize(&tmp_project.root).unwrap();
+        assert_eq!(
+            project.config_file.parent(),
+            Some(root.join(CONFIG_DIR).as_path())
+        );
+        assert_eq!(
+            project.config.image_source,
+            ImageSource::Dockerfile(
+                root.join(CONFIG_DIR)
+                    .join("Dockerfile")
+                    .display()
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_project_from_sub_directory() {
+        let json = r#"{ "image": "test" }"#;
+        let tmp_project = TmpProjectDir::new("aaa").devcontainer_json(json);
+        let sub_dir = tmp_project.root.join("src").join("bin");
+        std::fs::create_dir_all(&sub_dir).unwrap();
+
+        let project = Project::try_from(&sub_dir).unwrap();
+        let root = std::fs::canonicalize(&tmp_project.root).unwrap();
+        assert_eq!(project.root, root);
+        assert_eq!(project.config_file, root.join(CONFIG_DIR).join(CONFIG_FILE));
+        assert_eq!(project.name, "aaa");
+    }
+
+    #[test]
+    fn test_project_root_config_file() {
+        let tmp_project = TmpProjectDir::new("aaa");
+        std::fs::write(
+            tmp_project.root.join(ROOT_CONFIG_FILE),
+            r#"{ "dockerFile": "Dockerfile" }"#,
+        )
+        .unwrap();
+
+        let project = Project::try_from(&tmp_project.root).unwrap();
+        let root = std::fs::canonicalize(&tmp_project.root).unwrap();
+        assert_eq!(project.config_file, root.join(ROOT_CONFIG_FILE));
+        assert_eq!(project.config_file.parent(), Some(root.as_path()));
+        assert_eq!(
+            project.config.image_source,
+            ImageSource::Dockerfile(root.join("Dockerfile").display().to_string())
+        );
+    }
+
+    #[test]
+    fn test_project_search_stops_at_git_boundary() {
+        let outer = TmpProjectDir::new("outer").devcontainer_json(r#"{ "image": "test" }"#);
+        let repo = outer.root.join("repo");
+        std::fs::create_dir_all(repo.join(GIT_DIR)).unwrap();
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+
+        let err = Project::try_from(&repo.join("src")).unwrap_err();
+        let msg = err.to_string();
+        let repo = std::fs::canonicalize(&repo).unwrap();
+        assert!(msg.starts_with("no devcontainer config found, searched:"));
+        assert!(msg.contains(
+            &repo
+                .join("src")
+                .join(ROOT_CONFIG_FILE)
+                .display()
+                .to_string()
+        ));
+        assert!(msg.contains(
+            &repo
+                .join(CONFIG_DIR)
+                .join(CONFIG_FILE)
+                .display()
+                .to_string()
+        ));
+        assert!(!msg.contains(&format!("outer{}{}", std::path::MAIN_SEPARATOR, CONFIG_DIR)));
+    }
+
+    fn write_config(root: &Path, name: &str, json: &str) {
+        let dir = root.join(CONFIG_DIR).join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(CONFIG_FILE), json).unwrap();
+    }
+
+    #[test]
+    fn test_find_named_configs() {
+        let tmp_project = TmpProjectDir::new("aaa");
+        write_config(&tmp_project.root, "rust", r#"{ "image": "rust" }"#);
+        write_config(&tmp_project.root, "docs", r#"{ "image": "docs" }"#);
+        std::fs::create_dir_all(tmp_project.root.join(CONFIG_DIR).join("empty")).unwrap();
+
+        let configs = find_configs(&tmp_project.root).unwrap();
+        let names: Vec<_> = configs.iter().map(|c| c.name.as_deref()).collect();
+        assert_eq!(names, [Some("docs"), Some("rust")]);
+        assert_eq!(configs[0].root, tmp_project.root);
+        assert_eq!(
+            configs[0].to_string(),
+            "docs (.devcontainer/docs/devcontainer.json)"
+        );
+
+        let err = Project::try_from(&tmp_project.root).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("multiple devcontainer configs found"));
+    }
+
+    #[test]
+    fn test_load_named_config() {
+        let tmp_project = TmpProjectDir::new("aaa").devcontainer_json(r#"{ "image": "default" }"#);
+        write_config(
+            &tmp_project.root,
+            "gpu-less-ml",
+            r#"{ "dockerFile": "Dockerfile" }"#,
+        );
+        let root = std::fs::canonicalize(&tmp_project.root).unwrap();
+
+        let project =
+            Project::load(&tmp_project.root, Some("gpu-less-ml"), |_| unreachable!()).unwrap();
+        assert_eq!(project.name, "aaa_gpu-less-ml");
+        assert_eq!(project.root, root);
+        assert_eq!(
+            project.config.image_source,
+            ImageSource::Dockerfile(
+                root.join(CONFIG_DIR)
+                    .join("gpu-less-ml")
+                    .join("Dockerfile")
+                    .display()
+                    .to_string()
+            )
+        );
+
+        let project = Project::load(&tmp_project.root, None, |configs| {
+            assert_eq!(configs.len(), 2);
+            Ok(0)
+        })
+        .unwrap();
+        assert_eq!(project.name, "aaa");
+
+        let err = Project::load(&tmp_project.root, Some("docs"), |_| unreachable!()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "devcontainer config \"docs\" not found, available: gpu-less-ml"
+        );
+    }
+
+    #[test]
+    fn test_load_config_by_path() {
+        let tmp_project = TmpProjectDir::new("aaa");
+        write_config(&tmp_project.root, "rust", r#"{ "image": "rust" }"#);
+        write_config(&tmp_project.root, "docs", r#"{ "image": "docs" }"#);
+        let path = tmp_project
+            .root
+            .join(CONFIG_DIR)
+            .join("rust")
+            .join(CONFIG_FILE);
+
+        let project = Project::load(
+            &tmp_project.root.join(CONFIG_DIR),
+            Some(path.to_str().unwrap()),
+            |_| unreachable!(),
+        )
+        .unwrap();
+        assert_eq!(project.name, "aaa_rust");
+        assert_eq!(
+            project.root,
+            std::fs::canonicalize(&tmp_project.root).unwrap()
+        );
+        assert_eq!(
+            project.config.image_source,
+            ImageSource::Image("rust".to_string())
+        );
+
+        let project = Project::load(
+            &tmp_project.root.join(CONFIG_DIR).join("rust"),
+            Some(".devcontainer/docs/devcontainer.json"),
+            |_| unreachable!(),
+        )
+        .unwrap();
+        assert_eq!(project.name, "aaa_docs");
+
+        let err =
+            Project::load(&tmp_project.root, Some("other.json"), |_| unreachable!()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "devcontainer config file \"other.json\" not found"
+        );
+    }
+
+    #[test]
+    fn test_container_name() {
+        assert_eq!(container_name("taskcommander_dev"), "taskcommander_dev");
+        assert_eq!(container_name("My App@2:x"), "My_App_2_x");
+        assert_eq!(container_name("_.api"), "api");
+        assert_eq!(container_name("日本"), "devspace");
+    }
+
+    #[test]
+    fn test_image_name() {
+        assert_eq!(image_name("taskcommander_dev"), "taskcommander_dev");
+        assert_eq!(image_name("My App@2:x"), "my-app-2-x");
+        assert_eq!(image_name("--api__v2.."), "api_v2");
+        assert_eq!(image_name("@@@"), "devspace");
+    }
+
+    #[test]
+    fn test_project_id() {
+        let json = r#"{ "image": "test" }"#;
+        let a = TmpProjectDir::new("api").devcontainer_json(json);
+        let b = TmpProjectDir::new("api").devcontainer_json(json);
+
+        let project_a = Project::try_from(&a.root).unwrap();
+        let project_b = Project::try_from(&b.root).unwrap();
+        assert_eq!(project_a.name, project_b.name);
+        assert_ne!(project_a.id, project_b.id);
+        assert_ne!(project_a.container_name, project_b.container_name);
+        assert_ne!(project_a.image_name, project_b.image_name);
+
+        let again = Project::try_from(&a.root.join(CONFIG_DIR)).unwrap();
+        assert_eq!(project_a.id, again.id);
+        assert_eq!(project_a.id.len(), 64);
+        assert_eq!(
+            project_a.container_name,
+            format!("api-{}", &project_a.id[..SHORT_ID_LEN])
+        );
+        assert_eq!(
+            project_a.image_name,
+            format!("api-{}", &project_a.id[..SHORT_ID_LEN])
+        );
+    }
+
+    #[test]
+    fn test_project_id_per_config() {
+        let tmp_project = TmpProjectDir::new("aaa");
+        write_config(
+            &tmp_project.root,
+            "rust",
+            r#"{ "name": "App", "image": "rust" }"#,
+        );
+        write_config(
+            &tmp_project.root,
+            "docs",
+            r#"{ "name": "App", "image": "docs" }"#,
+        );
+
+        let rust = Project::load(&tmp_project.root, Some("rust"), |_| unreachable!()).unwrap();
+        let docs = Project::load(&tmp_project.root, Some("docs"), |_| unreachable!()).unwrap();
+        assert_ne!(rust.id, docs.id);
+        assert!(rust.image_name.starts_with("app_rust-"));
+        assert!(rust.container_name.starts_with("App_rust-"));
+    }
+
     pub struct TmpProjectDir {
         pub name: String,
         _tmpdir: TempDir,
@@ -0,0 +1,300 @@
+use crate::diagnostic::Diagnostic;
+use jsonc_parser::{ast, common::Ranged, parse_to_ast};
+use jsonschema::{
+    error::ValidationErrorKind, paths::PathChunk, Draft, JSONSchema, ValidationError,
+};
+use serde_json::{json, Value};
+use std::sync::OnceLock;
+
+/// the devcontainer.json schema of the spec, devContainer.base.schema.json
+const BASE_SCHEMA: &str = include_str!("../schemas/devContainer.base.schema.json");
+
+/// devspace extensions, checked on top of the base schema
+const EXTENSIONS: &str = include_str!("../schemas/devspace.schema.json");
+
+/// properties selecting where the container image comes from, only one may be set, with the
+/// definitions of the base schema a config using them has to match
+const IMAGE_SOURCES: &[(&str, &[&str])] = &[
+    ("image", &["imageContainer", "nonComposeBase"]),
+    (
+        "dockerFile",
+        &["dockerfileContainer/oneOf/1", "nonComposeBase"],
+    ),
+    (
+        "build.dockerfile",
+        &["dockerfileContainer/oneOf/0", "nonComposeBase"],
+    ),
+    ("dockerComposeFile", &["composeContainer"]),
+];
+
+struct Schemas {
+    config: JSONSchema,
+    /// one per image source, the base schema reports any violation as a mismatch of its root
+    /// `oneOf`, these tell what is wrong for the image source in use
+    sources: Vec<JSONSchema>,
+}
+
+fn schemas() -> &'static Schemas {
+    static SCHEMAS_CELL: OnceLock<Schemas> = OnceLock::new();
+    SCHEMAS_CELL.get_or_init(|| {
+        let base: Value = serde_json::from_str(BASE_SCHEMA).expect("embedded schema is valid json");
+        let extensions: Value =
+            serde_json::from_str(EXTENSIONS).expect("embedded schema is valid json");
+        let compile = |all_of: Vec<Value>| {
+            let mut schema = base.clone();
+            let root = schema
+                .as_object_mut()
+                .expect("embedded schema is an object");
+            root.remove("oneOf");
+            // unknown properties are warnings, reported when loading the config
+            root.remove("unevaluatedProperties");
+            root.insert("allOf".to_string(), Value::Array(all_of));
+            JSONSchema::options()
+                .with_draft(Draft::Draft201909)
+                .compile(&schema)
+                .expect("embedded schema compiles")
+        };
+        let reference =
+            |definition: &str| json!({ "$ref": format!("#/definitions/{}", definition) });
+
+        Schemas {
+            config: compile(vec![json!({ "oneOf": base["oneOf"] }), extensions]),
+            sources: IMAGE_SOURCES
+                .iter()
+                .map(|(_, definitions)| {
+                    let mut all_of: Vec<Value> = definitions.iter().map(|d| reference(d)).collect();
+                    all_of.push(reference("devContainerCommon"));
+                    compile(all_of)
+                })
+                .collect(),
+        }
+    })
+}
+
+/// validate a parsed devcontainer config against the devcontainer schema, `content` is the
+/// source the value was parsed from, returns every violation found
+pub fn validate(value: &Value, content: &str) -> Vec<Diagnostic> {
+    let schemas = schemas();
+    let errors = match schemas.config.validate(value) {
+        Ok(()) => return vec![],
+        Err(errors) => errors,
+    };
+    let sources: Vec<usize> = (0..IMAGE_SOURCES.len())
+        .filter(|&i| {
+            let (source, _) = IMAGE_SOURCES[i];
+            source
+                .split('.')
+                .try_fold(value, |v, key| v.get(key))
+                .is_some()
+        })
+        .collect();
+    let all: Vec<usize> = (0..IMAGE_SOURCES.len()).collect();
+    let quoted = |keys: &[usize]| {
+        keys.iter()
+            .map(|&i| format!("{:?}", IMAGE_SOURCES[i].0))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut violations = vec![];
+    for err in errors {
+        let root_mismatch = err.instance_path.iter().next().is_none()
+            && matches!(
+                err.kind,
+                ValidationErrorKind::OneOfNotValid | ValidationErrorKind::OneOfMultipleValid
+            );
+        if !root_mismatch {
+            violations.push(describe(&err));
+            continue;
+        }
+
+        let Some(&first) = sources.first() else {
+            violations.push((format!("one of {} is required", quoted(&all)), vec![]));
+            continue;
+        };
+        if let Some(&second) = sources.get(1) {
+            violations.push((
+                format!(
+                    "only one of {} may be set, found {}",
+                    quoted(&all),
+                    quoted(&sources)
+                ),
+                // point at the second one, the first is fine on its own
+                IMAGE_SOURCES[second]
+                    .0
+                    .split('.')
+                    .map(|k| PathChunk::Property(k.into()))
+                    .collect(),
+            ));
+        }
+        if let Err(errors) = schemas.sources[first].validate(value) {
+            violations.extend(errors.map(|e| describe(&e)));
+        }
+    }
+
+    let ast = parse_to_ast(content, &Default::default(), &Default::default())
+        .ok()
+        .and_then(|r| r.value);
+    let mut diagnostics: Vec<Diagnostic> = violations
+        .into_iter()
+        .map(
+            |(message, path)| match ast.as_ref().and_then(|ast| offset(ast, &path)) {
+                Some(offset) => Diagnostic::at(message, content, offset),
+                None => Diagnostic::new(message),
+            },
+        )
+        .collect();
+    diagnostics.sort_by_key(|d| d.location.as_ref().map(|l| (l.line, l.column)));
+    diagnostics
+}
+
+/// a readable message for the error and the path of the value to point at
+fn describe(err: &ValidationError) -> (String, Vec<PathChunk>) {
+    let path: Vec<PathChunk> = err.instance_path.iter().cloned().collect();
+    if path.is_empty() {
+        (err.to_string(), path)
+    } else {
+        (format!("{}: {}", pointer(&path), err), path)
+    }
+}
+
+/// dotted form of an instance path, e.g. `mounts.0.type`
+fn pointer(path: &[PathChunk]) -> String {
+    path.iter()
+        .map(|chunk| match chunk {
+            PathChunk::Property(name) => name.to_string(),
+            PathChunk::Index(i) => i.to_string(),
+            PathChunk::Keyword(k) => k.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// source offset of the value at `path`
+fn offset(value: &ast::Value, path: &[PathChunk]) -> Option<usize> {
+    match (path.split_first(), value) {
+        (None, value) => Some(value.range().start),
+        (Some((PathChunk::Property(name), rest)), ast::Value::Object(object)) => object
+            .properties
+            .iter()
+            .find(|p| p.name.as_str() == name.as_ref())
+            .and_then(|p| offset(&p.value, rest)),
+        (Some((PathChunk::Index(i), rest)), ast::Value::Array(array)) => {
+            array.elements.get(*i).and_then(|e| offset(e, rest))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonc_parser::parse_to_serde_value;
+
+    fn check(content: &str) -> Vec<Diagnostic> {
+        let value = parse_to_serde_value(content, &Default::default())
+            .unwrap()
+            .unwrap();
+        validate(&value, content)
+    }
+
+    #[test]
+    fn test_valid() {
+        let content = r#"{
+            // comments and trailing commas are fine
+            "image": "alpine",
+            "postCreateCommand": { "a": "echo a", "b": ["echo", "b"] },
+            "mounts": ["source=/a,target=/b,type=bind", { "type": "volume", "target": "/c" }],
+            "forwardPorts": [3000, "db:5432"],
+        }"#;
+        assert_eq!(check(content), vec![]);
+
+        let compose =
+            r#"{ "dockerComposeFile": ["a.yml"], "service": "app", "workspaceFolder": "/app" }"#;
+        assert_eq!(check(compose), vec![]);
+    }
+
+    #[test]
+    fn test_all_violations() {
+        let content = r#"{
+            "image": "alpine",
+            "dockerFile": "Dockerfile",
+            "shutdownAction": "halt",
+            "runArgs": ["--init", 1]
+        }"#;
+        let diagnostics = check(content);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert!(messages.contains(
+            &"only one of \"image\", \"dockerFile\", \"build.dockerfile\", \"dockerComposeFile\" may be set, found \"image\", \"dockerFile\""
+        ));
+        let enum_error = diagnostics
+            .iter()
+            .find(|d| d.message.starts_with("shutdownAction: \"halt\""))
+            .unwrap();
+        assert_eq!(enum_error.location.as_ref().unwrap().line, 4);
+        let type_error = diagnostics
+            .iter()
+            .find(|d| d.message.starts_with("runArgs.1: 1 is not of type"))
+            .unwrap();
+        assert_eq!(type_error.location.as_ref().unwrap().line, 5);
+    }
+
+    #[test]
+    fn test_source_violations() {
+        let content = r#"{
+            "dockerFile": "Dockerfile",
+            "build": { "target": 1 },
+            "portsAttributes": { "3000": { "onAutoForward": "open" } }
+        }"#;
+        let messages: Vec<_> = check(content).into_iter().map(|d| d.message).collect();
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert!(messages[0].starts_with("build.target: 1 is not of type"));
+        assert!(messages[1].starts_with("portsAttributes.3000.onAutoForward: \"open\""));
+    }
+
+    #[test]
+    fn test_extensions() {
+        let content = r#"{
+            "image": "alpine",
+            "shell": "zsh",
+            "customizations": { "devspace": { "cpus": 1.5, "pidsLimit": 100 }, "vscode": {} }
+        }"#;
+        assert_eq!(check(content), vec![]);
+
+        let content = r#"{
+            "image": "alpine",
+            "shell": ["zsh"],
+            "customizations": { "devspace": { "cpu": 1 } }
+        }"#;
+        let messages: Vec<_> = check(content).into_iter().map(|d| d.message).collect();
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert!(messages[0].starts_with("shell: [\"zsh\"] is not of type"));
+        assert!(messages[1].starts_with("customizations.devspace: Additional properties"));
+    }
+
+    #[test]
+    fn test_missing_image_source() {
+        let messages: Vec<_> = check(r#"{ "name": "aaa" }"#)
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            ["one of \"image\", \"dockerFile\", \"build.dockerfile\", \"dockerComposeFile\" is required"]
+        );
+
+        let messages: Vec<_> = check(r#"{ "dockerComposeFile": "a.yml" }"#)
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "\"service\" is a required property",
+                "\"workspaceFolder\" is a required property"
+            ]
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use crate::project::{CONFIG_DIR, CONFIG_FILE};
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+
+const NAME_PLACEHOLDER: &str = "{{name}}";
+const DOCKERFILE: &str = "Dockerfile";
+/// template used when no marker file is found
+const FALLBACK: &str = "base";
+
+struct Builtin {
+    name: &'static str,
+    /// files in the project root identifying the project type
+    markers: &'static [&'static str],
+    config: &'static str,
+    dockerfile: Option<&'static str>,
+}
+
+const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "rust",
+        markers: &["Cargo.toml"],
+        config: include_str!("../templates/rust/devcontainer.json"),
+        dockerfile: Some(include_str!("../templates/rust/Dockerfile")),
+    },
+    Builtin {
+        name: "node",
+        markers: &["package.json"],
+        config: include_str!("../templates/node/devcontainer.json"),
+        dockerfile: Some(include_str!("../templates/node/Dockerfile")),
+    },
+    Builtin {
+        name: "go",
+        markers: &["go.mod"],
+        config: include_str!("../templates/go/devcontainer.json"),
+        dockerfile: Some(include_str!("../templates/go/Dockerfile")),
+    },
+    Builtin {
+        name: "python",
+        markers: &["pyproject.toml", "setup.py", "requirements.txt"],
+        config: include_str!("../templates/python/devcontainer.json"),
+        dockerfile: Some(include_str!("../templates/python/Dockerfile")),
+    },
+    Builtin {
+        name: FALLBACK,
+        markers: &[],
+        config: include_str!("../templates/base/devcontainer.json"),
+        dockerfile: None,
+    },
+];
+
+/// files written to the `.devcontainer` dir of a project by `devspace init`
+#[derive(Debug)]
+pub struct Template {
+    pub name: String,
+    /// file names and contents, `{{name}}` is replaced by the project name
+    files: Vec<(String, String)>,
+}
+
+impl Template {
+    /// the template called `name`, a user template in `user_dir` takes precedence over the
+    /// builtin one with the same name
+    pub fn find(name: &str, user_dir: &Path) -> Result<Self> {
+        let dir = user_dir.join(name);
+        if dir.is_dir() {
+            return Template::from_dir(name, &dir);
+        }
+        Template::builtin(name).ok_or_else(|| {
+            anyhow!(
+                "template {:?} not found, available: {}",
+                name,
+                names(user_dir).join(", ")
+            )
+        })
+    }
+
+    fn builtin(name: &str) -> Option<Self> {
+        let builtin = BUILTINS.iter().find(|b| b.name == name)?;
+        let mut files = vec![(CONFIG_FILE.to_string(), builtin.config.to_string())];
+        if let Some(dockerfile) = builtin.dockerfile {
+            files.push((DOCKERFILE.to_string(), dockerfile.to_string()));
+        }
+        Some(Template {
+            name: name.to_string(),
+            files,
+        })
+    }
+
+    /// a user template is a directory holding a devcontainer.json and any other files to
+    /// copy next to it
+    fn from_dir(name: &str, dir: &Path) -> Result<Self> {
+        if !dir.join(CONFIG_FILE).is_file() {
+            return Err(anyhow!("template {:?} has no {}", dir, CONFIG_FILE));
+        }
+        let mut files = vec![];
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read template file {:?}", path))?;
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            files.push((file_name.to_string(), content));
+        }
+        files.sort();
+        Ok(Template {
+            name: name.to_string(),
+            files,
+        })
+    }
+
+    /// write the template files to the `.devcontainer` dir of `root`, existing files are only
+    /// replaced with `force`, returns the written paths
+    pub fn write(&self, root: &Path, project_name: &str, force: bool) -> Result<Vec<PathBuf>> {
+        let config_dir = root.join(CONFIG_DIR);
+        let paths: Vec<PathBuf> = self
+            .files
+            .iter()
+            .map(|(name, _)| config_dir.join(name))
+            .collect();
+
+        if !force {
+            let existing: Vec<String> = paths
+                .iter()
+                .filter(|p| p.exists())
+                .map(|p| format!("  {}", p.display()))
+                .collect();
+            if !existing.is_empty() {
+                return Err(anyhow!(
+                    "refusing to overwrite existing files, use --force to replace them:\n{}",
+                    existing.join("\n")
+                ));
+            }
+        }
+
+        // the name ends up inside json strings
+        let escaped = serde_json::to_string(project_name)?;
+        let escaped = &escaped[1..escaped.len() - 1];
+        std::fs::create_dir_all(&config_dir)
+            .with_context(|| format!("failed to create {:?}", config_dir))?;
+        for ((_, content), path) in self.files.iter().zip(&paths) {
+            std::fs::write(path, content.replace(NAME_PLACEHOLDER, escaped))
+                .with_context(|| format!("failed to write {:?}", path))?;
+        }
+        Ok(paths)
+    }
+}
+
+/// name of the builtin template matching the files in `root`
+pub fn detect(root: &Path) -> &'static str {
+    BUILTINS
+        .iter()
+        .find(|b| b.markers.iter().any(|m| root.join(m).exists()))
+        .map(|b| b.name)
+        .unwrap_or(FALLBACK)
+}
+
+/// names of the builtin and user templates
+pub fn names(user_dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = BUILTINS.iter().map(|b| b.name.to_string()).collect();
+    if let Ok(entries) = std::fs::read_dir(user_dir) {
+        names.extend(
+            entries
+                .filter_map(|e| e.ok())
+                .filter(|e| e.path().is_dir())
+                .filter_map(|e| e.file_name().to_str().map(|n| n.to_string())),
+        );
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::Project;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect() {
+        let tmpdir = TempDir::new().unwrap();
+        assert_eq!(detect(tmpdir.path()), "base");
+        std::fs::write(tmpdir.path().join("requirements.txt"), "").unwrap();
+        assert_eq!(detect(tmpdir.path()), "python");
+        std::fs::write(tmpdir.path().join("Cargo.toml"), "").unwrap();
+        assert_eq!(detect(tmpdir.path()), "rust");
+    }
+
+    #[test]
+    fn test_builtins_are_valid() {
+        for builtin in BUILTINS {
+            let tmpdir = TempDir::new().unwrap();
+            let root = tmpdir.path().join("aaa");
+            let template = Template::find(builtin.name, tmpdir.path()).unwrap();
+            template.write(&root, "a \"b\"", false).unwrap();
+            let project = Project::try_from(&root).unwrap();
+            assert_eq!(project.config.name.as_deref(), Some("a \"b\""));
+        }
+    }
+
+    #[test]
+    fn test_write() {
+        let tmpdir = TempDir::new().unwrap();
+        let root = tmpdir.path().join("aaa");
+        let template = Template::find("rust", tmpdir.path()).unwrap();
+        let paths = template.write(&root, "aaa", false).unwrap();
+        assert_eq!(
+            paths,
+            [
+                root.join(CONFIG_DIR).join(CONFIG_FILE),
+                root.join(CONFIG_DIR).join(DOCKERFILE)
+            ]
+        );
+
+        std::fs::write(&paths[1], "FROM scratch").unwrap();
+        let err = template.write(&root, "aaa", false).unwrap_err();
+        assert!(err.to_string().contains("use --force"));
+        assert_eq!(std::fs::read_to_string(&paths[1]).unwrap(), "FROM scratch");
+
+        template.write(&root, "aaa", true).unwrap();
+        assert!(std::fs::read_to_string(&paths[1])
+            .unwrap()
+            .starts_with("FROM rust"));
+    }
+
+    #[test]
+    fn test_user_template() {
+        let tmpdir = TempDir::new().unwrap();
+        let user_dir = tmpdir.path().join("templates");
+        std::fs::create_dir_all(user_dir.join("rust")).unwrap();
+        std::fs::create_dir_all(user_dir.join("empty")).unwrap();
+        std::fs::write(
+            user_dir.join("rust").join(CONFIG_FILE),
+            r#"{ "name": "{{name}}", "image": "my-rust" }"#,
+        )
+        .unwrap();
+
+        let template = Template::find("rust", &user_dir).unwrap();
+        let root = tmpdir.path().join("aaa");
+        let paths = template.write(&root, "aaa", false).unwrap();
+        assert_eq!(paths, [root.join(CONFIG_DIR).join(CONFIG_FILE)]);
+        assert_eq!(
+            std::fs::read_to_string(&paths[0]).unwrap(),
+            r#"{ "name": "aaa", "image": "my-rust" }"#
+        );
+
+        assert!(Template::find("empty", &user_dir).is_err());
+        let err = Template::find("nope", &user_dir).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "template \"nope\" not found, available: base, empty, go, node, python, rust"
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use serde_json::Value;
+use std::{collections::HashMap, path::Path};
+
+/// values for the `${...}` variables of devcontainer.json, unknown variables and the ones
+/// only known inside the container like `${containerEnv:PATH}` are kept as they are
+#[derive(Debug, Default)]
+pub struct Variables {
+    pub local_workspace_folder: Option<String>,
+    pub container_workspace_folder: Option<String>,
+    pub devcontainer_id: Option<String>,
+    pub env: HashMap<String, String>,
+}
+
+impl Variables {
+    pub fn new(root: &Path, devcontainer_id: &str) -> Self {
+        Variables {
+            local_workspace_folder: Some(root.display().to_string()),
+            container_workspace_folder: None,
+            devcontainer_id: Some(devcontainer_id.to_string()),
+            env: std::env::vars().collect(),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<String> {
+        let basename = |p: &Option<String>| {
+            p.as_deref()
+                .map(Path::new)
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().to_string())
+        };
+        match name.split_once(':') {
+            Some(("localEnv" | "env", var)) => {
+                let (var, default) = match var.split_once(':') {
+                    Some((var, default)) => (var, default),
+                    None => (var, ""),
+                };
+                Some(self.env.get(var).cloned().unwrap_or(default.to_string()))
+            }
+            Some(_) => None,
+            None => match name {
+                "localWorkspaceFolder" => self.local_workspace_folder.clone(),
+                "localWorkspaceFolderBasename" => basename(&self.local_workspace_folder),
+                "containerWorkspaceFolder" => self.container_workspace_folder.clone(),
+                "containerWorkspaceFolderBasename" => basename(&self.container_workspace_folder),
+                "devcontainerId" => self.devcontainer_id.clone(),
+                _ => None,
+            },
+        }
+    }
+
+    /// replace the known variables in `s`
+    pub fn substitute_str(&self, s: &str) -> String {
+        let mut result = String::with_capacity(s.len());
+        let mut rest = s;
+        while let Some(start) = rest.find("${") {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            let name = &rest[start + 2..start + len];
+            result.push_str(&rest[..start]);
+            match self.lookup(name) {
+                Some(value) => result.push_str(&value),
+                None => result.push_str(&rest[start..=start + len]),
+            }
+            rest = &rest[start + len + 1..];
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// replace the known variables in all strings of `value`, object keys are left alone
+    pub fn substitute(&self, value: &mut Value) {
+        match value {
+            Value::String(s) => *s = self.substitute_str(s),
+            Value::Array(values) => values.iter_mut().for_each(|v| self.substitute(v)),
+            Value::Object(map) => map.values_mut().for_each(|v| self.substitute(v)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn variables() -> Variables {
+        Variables {
+            local_workspace_folder: Some("/src/aaa".to_string()),
+            container_workspace_folder: Some("/workspaces/aaa".to_string()),
+            devcontainer_id: Some("0123".to_string()),
+            env: HashMap::from([("HOME".to_string(), "/home/u".to_string())]),
+        }
+    }
+
+    #[test]
+    fn test_substitute_str() {
+        let vars = variables();
+        assert_eq!(
+            vars.substitute_str(
+                "source=${localWorkspaceFolder},target=${containerWorkspaceFolder}"
+            ),
+            "source=/src/aaa,target=/workspaces/aaa"
+        );
+        assert_eq!(
+            vars.substitute_str("${localWorkspaceFolderBasename}-${devcontainerId}"),
+            "aaa-0123"
+        );
+        assert_eq!(vars.substitute_str("${localEnv:HOME}/.ssh"), "/home/u/.ssh");
+        assert_eq!(vars.substitute_str("${localEnv:NOPE:x}"), "x");
+        assert_eq!(vars.substitute_str("${localEnv:NOPE}"), "");
+        assert_eq!(
+            vars.substitute_str("${containerEnv:PATH}"),
+            "${containerEnv:PATH}"
+        );
+        assert_eq!(vars.substitute_str("${unclosed"), "${unclosed");
+    }
+
+    #[test]
+    fn test_substitute() {
+        let mut value = json!({
+            "mounts": ["source=${localEnv:HOME},target=/h,type=bind"],
+            "containerEnv": { "${localEnv:HOME}": "${devcontainerId}" },
+            "init": true,
+        });
+        variables().substitute(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "mounts": ["source=/home/u,target=/h,type=bind"],
+                "containerEnv": { "${localEnv:HOME}": "0123" },
+                "init": true,
+            })
+        );
+    }
+}